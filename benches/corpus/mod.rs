@@ -0,0 +1,220 @@
+//! Grids shared by the benchmarks, from 5x5 to 21x21. Names give the size, difficulty and
+//! kind: themeless grids start blank, themed grids have a few prefilled entries and partial
+//! grids have whole regions filled in.
+//!
+//! With the default word list and seed 1, easy grids fill in well under a second. Hard ones
+//! backtrack for seconds or much longer, so they are left out of time-to-fill benchmarks.
+
+// Each benchmark uses part of the corpus.
+#![allow(dead_code)]
+
+use fillers::crossword::Crossword;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Difficulty {
+    Easy,
+    Hard,
+}
+
+pub struct Grid {
+    pub name: &'static str,
+    pub difficulty: Difficulty,
+    pub rows: &'static [&'static str],
+}
+
+impl Grid {
+    pub fn crossword(&self) -> Crossword {
+        Crossword::from_string(self.rows.concat(), self.rows[0].len(), self.rows.len()).unwrap()
+    }
+}
+
+pub const CORPUS: &[Grid] = &[
+    Grid {
+        name: "5x5-easy-themeless",
+        difficulty: Difficulty::Easy,
+        rows: &["     ", "     ", "     ", "     ", "     "],
+    },
+    Grid {
+        name: "9x9-easy-themeless",
+        difficulty: Difficulty::Easy,
+        rows: &[
+            "*     ***",
+            "*       *",
+            "        *",
+            "     *   ",
+            "    *    ",
+            "   *     ",
+            "*        ",
+            "*       *",
+            "***     *",
+        ],
+    },
+    Grid {
+        name: "9x9-hard-themeless",
+        difficulty: Difficulty::Hard,
+        rows: &[
+            "   ***   ",
+            "    *    ",
+            "         ",
+            "*        ",
+            "         ",
+            "        *",
+            "         ",
+            "    *    ",
+            "   ***   ",
+        ],
+    },
+    Grid {
+        name: "11x11-easy-themeless",
+        difficulty: Difficulty::Easy,
+        rows: &[
+            "*   ****   ",
+            "           ",
+            "           ",
+            "   *    ***",
+            "    *     *",
+            "           ",
+            "*     *    ",
+            "***    *   ",
+            "           ",
+            "           ",
+            "   ****   *",
+        ],
+    },
+    Grid {
+        name: "13x13-hard-themeless",
+        difficulty: Difficulty::Hard,
+        rows: &[
+            "*    **     *",
+            "     *       ",
+            "             ",
+            "             ",
+            "*   *    *   ",
+            "*    *     **",
+            "**    *    **",
+            "**     *    *",
+            "   *    *   *",
+            "             ",
+            "             ",
+            "       *     ",
+            "*     **    *",
+        ],
+    },
+    Grid {
+        name: "15x15-easy-themed",
+        difficulty: Difficulty::Easy,
+        rows: &[
+            "STRAWBERRY*    ",
+            "          *    ",
+            "          *    ",
+            "   *    **     ",
+            "***   **       ",
+            "**         *   ",
+            "*         *    ",
+            "     *   *     ",
+            "    *         *",
+            "   *         **",
+            "       **   ***",
+            "     **    *   ",
+            "    *          ",
+            "    *          ",
+            "    *          ",
+        ],
+    },
+    Grid {
+        name: "15x15-easy-themeless",
+        difficulty: Difficulty::Easy,
+        rows: &[
+            "   **   *      ",
+            "        *      ",
+            "        *      ",
+            "     *   **    ",
+            "    **   *     ",
+            "*    **     ***",
+            "      *    *   ",
+            "      ***      ",
+            "   *    *      ",
+            "***     **    *",
+            "     *   **    ",
+            "    **   *     ",
+            "      *        ",
+            "      *        ",
+            "      *   **   ",
+        ],
+    },
+    Grid {
+        name: "15x15-easy-partial",
+        difficulty: Difficulty::Easy,
+        rows: &[
+            "JETBD**CSU*EDT*",
+            "ETHANALLEN*XORO",
+            "WEIGHTMONEYCLIP",
+            "SRS**HIAS*MULCH",
+            "*NINO**KAWASAKI",
+            "WISEMAN**USER**",
+            "OZONEHOLES**SAB",
+            "   *       *   ",
+            "   **          ",
+            "**    **       ",
+            "        **    *",
+            "     *    **   ",
+            "               ",
+            "    *          ",
+            "*   *   **     ",
+        ],
+    },
+    Grid {
+        name: "21x21-easy-themed",
+        difficulty: Difficulty::Easy,
+        rows: &[
+            "*****   ***   ***   *",
+            "   **    **    **    ",
+            "    *     *     *    ",
+            "     *     *     *   ",
+            "*JOLLY*YOWLS*     ***",
+            "**     *     *     **",
+            "***     *     *     *",
+            "   *     *     *     ",
+            "    *     *     *    ",
+            "     *     *     *   ",
+            "*     *SOWER*     ***",
+            "**     *     *     **",
+            "***     *     *     *",
+            "   *     *     *     ",
+            "    *     *     *    ",
+            "     *     *     *   ",
+            "*AESOP*FURRY*     ***",
+            "**     *     *     **",
+            "***     *     *     *",
+            "****    **    **    *",
+            "*****   ***   ***   *",
+        ],
+    },
+    Grid {
+        name: "21x21-hard-themeless",
+        difficulty: Difficulty::Hard,
+        rows: &[
+            "      *    *         ",
+            "      *    *         ",
+            "      *    *         ",
+            "***       *   *      ",
+            "    *        *       ",
+            "       *     *    ***",
+            "     **     *    *   ",
+            "      *         **   ",
+            "***      **    *     ",
+            "   **   *      *     ",
+            "       **   **       ",
+            "     *      *   **   ",
+            "     *    **      ***",
+            "   **         *      ",
+            "   *    *     **     ",
+            "***    *     *       ",
+            "       *        *    ",
+            "      *   *       ***",
+            "         *    *      ",
+            "         *    *      ",
+            "         *    *      ",
+        ],
+    },
+];
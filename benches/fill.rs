@@ -0,0 +1,70 @@
+//! Time to fill each easy grid of the corpus from scratch, and the rate at which the search
+//! expands nodes on every grid once its cache is warm.
+
+mod corpus;
+
+use corpus::{Difficulty, CORPUS};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fillers::{
+    crossword::Crossword,
+    fill::{Fill, FillOptions, Filler},
+    index::Index,
+};
+
+/// Most nodes expanded per iteration of a node rate benchmark, so hard grids finish too.
+const NODE_BUDGET: usize = 20_000;
+
+fn options() -> FillOptions {
+    FillOptions {
+        seed: Some(1),
+        ..FillOptions::default()
+    }
+}
+
+/// Searches until the fill ends or `NODE_BUDGET` nodes were expanded, returning the number
+/// of nodes expanded.
+fn expand(filler: &mut Filler, crossword: &Crossword) -> usize {
+    let mut search = filler.start(crossword, None);
+    filler.step(&mut search, NODE_BUDGET);
+    search.nodes()
+}
+
+fn time_to_fill(c: &mut Criterion) {
+    let index = Index::build_default();
+    let options = options();
+
+    let mut group = c.benchmark_group("time_to_fill");
+    group.sample_size(10);
+    for grid in CORPUS.iter().filter(|g| g.difficulty == Difficulty::Easy) {
+        let crossword = grid.crossword();
+        group.bench_function(grid.name, |b| {
+            b.iter(|| {
+                Filler::with_options(&index, &options)
+                    .fill(&crossword, None)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn nodes_per_sec(c: &mut Criterion) {
+    let index = Index::build_default();
+    let options = options();
+
+    let mut group = c.benchmark_group("nodes_per_sec");
+    group.sample_size(10);
+    for grid in CORPUS {
+        let crossword = grid.crossword();
+        // The first search warms the cache, leaving mostly the search itself to measure. Seeded
+        // searches expand the same nodes every time.
+        let mut filler = Filler::with_options(&index, &options);
+        let nodes = expand(&mut filler, &crossword);
+        group.throughput(Throughput::Elements(nodes as u64));
+        group.bench_function(grid.name, |b| b.iter(|| expand(&mut filler, &crossword)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, time_to_fill, nodes_per_sec);
+criterion_main!(benches);
@@ -0,0 +1,58 @@
+//! Building and decoding the default index, and looking up the entry patterns of the corpus.
+
+mod corpus;
+
+use corpus::CORPUS;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fillers::{crossword::EntryIterator, index::Index};
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("index");
+    group.sample_size(10);
+    group.bench_function("build_default", |b| b.iter(Index::build_default));
+    let bytes = Index::build_default().to_bytes();
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| Index::from_bytes(&bytes).unwrap())
+    });
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let index = Index::build_default();
+    let patterns: Vec<String> = CORPUS
+        .iter()
+        .flat_map(|grid| {
+            let crossword = grid.crossword();
+            crossword
+                .get_entries()
+                .iter()
+                .map(|entry_location| EntryIterator::new(&crossword, entry_location).collect())
+                .collect::<Vec<String>>()
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("lookup");
+    // Blank patterns of long entries match thousands of words each.
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(patterns.len() as u64));
+    group.bench_function("weighted_words", |b| {
+        b.iter(|| {
+            patterns
+                .iter()
+                .map(|pattern| index.weighted_words(pattern.chars()).len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("is_valid", |b| {
+        b.iter(|| {
+            patterns
+                .iter()
+                .filter(|pattern| index.is_valid(pattern.chars()))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, build, lookup);
+criterion_main!(benches);
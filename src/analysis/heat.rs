@@ -0,0 +1,181 @@
+//! How constrained each part of a grid is before filling it.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crossword::{Direction, EntryIterator},
+    fill::{cache::FillCache, filter::AllowedWords},
+    index::Index,
+    Crossword,
+};
+
+const ALL_LETTERS: u32 = (1 << 26) - 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryHeat {
+    pub start_row: usize,
+    pub start_col: usize,
+    pub direction: Direction,
+    pub length: usize,
+    /// Number of words fitting the entry as the grid stands. Prefilled entries are kept as
+    /// they are by the fill, so they count as 1 even when the index lacks their word.
+    pub candidates: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeatMap {
+    /// For each row and column, the number of letters both entries through the cell have a
+    /// word for, or `None` for blocks.
+    pub cells: Vec<Vec<Option<usize>>>,
+    pub entries: Vec<EntryHeat>,
+}
+
+/// Computes the heat map of `crossword`. Low numbers mark the risky areas: a cell with no
+/// feasible letter or an entry with no candidate cannot be filled as the grid stands.
+pub fn heat_map(index: &Index, cache: &mut FillCache, crossword: &Crossword) -> HeatMap {
+    let mut masks = vec![ALL_LETTERS; crossword.width * crossword.height];
+    let mut entries = vec![];
+    let allowed = AllowedWords::default();
+
+    for entry_location in crossword.get_entries() {
+        if entry_location.prefilled {
+            // The fill keeps the entry as it is, so each cell can only take its own letter.
+            for (row, col) in entry_location.cells() {
+                let cell = row * crossword.width + col;
+                match crossword.contents.as_bytes()[cell] {
+                    c @ b'A'..=b'Z' => masks[cell] &= 1 << (c - b'A'),
+                    _ => masks[cell] = 0,
+                }
+            }
+            entries.push(EntryHeat {
+                start_row: entry_location.start_row,
+                start_col: entry_location.start_col,
+                direction: entry_location.direction.clone(),
+                length: entry_location.length,
+                candidates: 1,
+            });
+            continue;
+        }
+        let iter = EntryIterator::new(crossword, &entry_location);
+        let words = cache.words.words(iter.clone(), index);
+        let mut order: Vec<usize> = (0..words.len()).collect();
+        allowed.apply(&iter, words, &mut order);
+
+        let mut letters = vec![0u32; entry_location.length];
+        for word in order.iter().map(|i| &words[*i].0) {
+            for (i, c) in word.bytes().enumerate() {
+                if c.is_ascii_uppercase() {
+                    letters[i] |= 1 << (c - b'A');
+                }
+            }
+        }
+        for (i, (row, col)) in entry_location.cells().enumerate() {
+            masks[row * crossword.width + col] &= letters[i];
+        }
+
+        entries.push(EntryHeat {
+            start_row: entry_location.start_row,
+            start_col: entry_location.start_col,
+            direction: entry_location.direction.clone(),
+            length: entry_location.length,
+            candidates: order.len(),
+        });
+    }
+
+    let cells = (0..crossword.height)
+        .map(|row| {
+            (0..crossword.width)
+                .map(|col| {
+                    let cell = row * crossword.width + col;
+                    match crossword.contents.as_bytes()[cell] {
+                        b'*' => None,
+                        _ => Some(masks[cell].count_ones() as usize),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    HeatMap { cells, entries }
+}
+
+impl fmt::Display for HeatMap {
+    /// Prints the feasible letter counts as a grid, followed by the entries from fewest
+    /// candidates up.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells.iter() {
+            for cell in row.iter() {
+                match cell {
+                    Some(count) => write!(f, "{:>3}", count)?,
+                    None => write!(f, "  #")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        let mut entries: Vec<&EntryHeat> = self.entries.iter().collect();
+        entries.sort_by_key(|e| (e.candidates, e.start_row, e.start_col));
+        for entry in entries {
+            writeln!(
+                f,
+                "{:?} {},{} ({} letters): {}",
+                entry.direction, entry.start_row, entry.start_col, entry.length, entry.candidates
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::heat_map;
+    use crate::{crossword::Direction, fill::cache::FillCache, index::Index, Crossword};
+
+    #[test]
+    fn feasible_letters() {
+        let index = Index::build(
+            ["CAT", "COT", "CH", "AT", "AX", "OX", "TO"]
+                .iter()
+                .map(|w| (String::from(*w), 0))
+                .collect(),
+        );
+        let grid = Crossword::from_string(String::from("      "), 3, 2).unwrap();
+
+        let heat = heat_map(&index, &mut FillCache::default(), &grid);
+        // No two-letter word ends in C, so the bottom left cell is a dead end.
+        assert_eq!(
+            heat.cells,
+            vec![
+                vec![Some(1), Some(2), Some(1)],
+                vec![Some(0), Some(1), Some(1)]
+            ]
+        );
+        let candidates: Vec<usize> = heat.entries.iter().map(|e| e.candidates).collect();
+        assert_eq!(candidates, vec![2, 2, 5, 5, 5]);
+
+        // The prefilled XQZ is not in the index, but the fill keeps it, so it is no dead end.
+        let index = Index::build(
+            ["XAB", "QCD", "ZEF", "ACE", "BDF"]
+                .iter()
+                .map(|w| (String::from(*w), 0))
+                .collect(),
+        );
+        let grid = Crossword::from_string(String::from("X  Q  Z  "), 3, 3).unwrap();
+        let heat = heat_map(&index, &mut FillCache::default(), &grid);
+        assert!(heat.cells.iter().all(|row| row[0] == Some(1)));
+        let down = heat
+            .entries
+            .iter()
+            .find(|e| e.direction == Direction::Down && e.start_col == 0)
+            .unwrap();
+        assert_eq!(down.candidates, 1);
+
+        // Solved cells have exactly one letter left.
+        let grid = Crossword::from_string(String::from("CATORETEE"), 3, 3).unwrap();
+        let heat = heat_map(&index, &mut FillCache::default(), &grid);
+        assert!(heat.cells.iter().flatten().all(|cell| *cell == Some(1)));
+        assert!(heat.entries.iter().all(|e| e.candidates == 1));
+    }
+}
@@ -0,0 +1,5 @@
+//! Read-only looks at a grid for the editor: what fits where, and how healthy a fill is.
+
+pub mod heat;
+pub mod report;
+pub mod suggest;
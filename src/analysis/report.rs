@@ -0,0 +1,176 @@
+//! Statistics about a finished (or partly finished) fill.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::fill::ReportEntry;
+use crate::{crossword::EntryIterator, index::Index, Crossword};
+
+/// Number of entries listed in `FillReport::lowest_weighted`.
+pub const LOWEST_WEIGHTED_ENTRIES: usize = 5;
+
+const SCRABBLE_VALUES: [u32; 26] = [
+    1, 3, 3, 2, 1, 4, 2, 4, 1, 8, 5, 1, 3, 1, 1, 3, 10, 1, 1, 1, 1, 4, 4, 8, 4, 10,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillReport {
+    pub word_count: usize,
+    pub average_length: f64,
+    pub block_count: usize,
+    /// Occurrences of each letter in the grid, counting every cell once.
+    pub letter_counts: BTreeMap<char, usize>,
+    pub pangram: bool,
+    /// Sum of the Scrabble tile values of the grid's letters, counting every cell once.
+    pub scrabble_score: u32,
+    /// Over complete entries found in the index; `None` when there are none.
+    pub average_weight: Option<f64>,
+    pub min_weight: Option<i32>,
+    /// The lowest-weighted complete entries, lowest first.
+    pub lowest_weighted: Vec<ReportEntry>,
+    /// Complete entries missing from the index.
+    pub not_in_index: Vec<ReportEntry>,
+}
+
+/// Reports on `crossword` as filled. Incomplete entries only count towards the word count and
+/// average length.
+pub fn fill_report(index: &Index, crossword: &Crossword) -> FillReport {
+    let entry_locations = crossword.get_entries();
+
+    let mut letter_counts = BTreeMap::new();
+    let mut scrabble_score = 0;
+    for c in crossword.contents.chars() {
+        if c.is_ascii_uppercase() {
+            *letter_counts.entry(c).or_insert(0) += 1;
+            scrabble_score += SCRABBLE_VALUES[(c as u8 - b'A') as usize];
+        }
+    }
+
+    let mut weighted = vec![];
+    let mut not_in_index = vec![];
+    for entry_location in entry_locations.iter() {
+        let word: String = EntryIterator::new(crossword, entry_location).collect();
+        if word.contains(' ') {
+            continue;
+        }
+        let entry = ReportEntry {
+            start_row: entry_location.start_row,
+            start_col: entry_location.start_col,
+            direction: entry_location.direction.clone(),
+            weight: index.get(&word).map(|w| w.weight),
+            word,
+        };
+        match entry.weight {
+            Some(_) => weighted.push(entry),
+            None => not_in_index.push(entry),
+        }
+    }
+    weighted.sort_by_key(|e| (e.weight, e.start_row, e.start_col));
+
+    let weights: Vec<i32> = weighted.iter().filter_map(|e| e.weight).collect();
+    let total_length: usize = entry_locations.iter().map(|e| e.length).sum();
+
+    FillReport {
+        word_count: entry_locations.len(),
+        average_length: match entry_locations.is_empty() {
+            true => 0.0,
+            false => total_length as f64 / entry_locations.len() as f64,
+        },
+        block_count: crossword.contents.chars().filter(|c| *c == '*').count(),
+        pangram: letter_counts.len() == 26,
+        letter_counts,
+        scrabble_score,
+        average_weight: match weights.is_empty() {
+            true => None,
+            false => Some(weights.iter().map(|w| *w as f64).sum::<f64>() / weights.len() as f64),
+        },
+        min_weight: weights.iter().min().copied(),
+        lowest_weighted: weighted.into_iter().take(LOWEST_WEIGHTED_ENTRIES).collect(),
+        not_in_index,
+    }
+}
+
+impl fmt::Display for ReportEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {},{}: {}",
+            self.direction, self.start_row, self.start_col, self.word
+        )?;
+        match self.weight {
+            Some(weight) => write!(f, " ({})", weight),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for FillReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Words: {}", self.word_count)?;
+        writeln!(f, "Average length: {:.2}", self.average_length)?;
+        writeln!(f, "Blocks: {}", self.block_count)?;
+        let letters: Vec<String> = self
+            .letter_counts
+            .iter()
+            .map(|(c, count)| format!("{}:{}", c, count))
+            .collect();
+        writeln!(f, "Letters: {}", letters.join(" "))?;
+        writeln!(f, "Pangram: {}", if self.pangram { "yes" } else { "no" })?;
+        writeln!(f, "Scrabble score: {}", self.scrabble_score)?;
+        if let (Some(average), Some(min)) = (self.average_weight, self.min_weight) {
+            writeln!(f, "Average weight: {:.2}", average)?;
+            writeln!(f, "Minimum weight: {}", min)?;
+        }
+        writeln!(f, "Lowest weighted:")?;
+        for entry in self.lowest_weighted.iter() {
+            writeln!(f, "  {}", entry)?;
+        }
+        if !self.not_in_index.is_empty() {
+            writeln!(f, "Not in index:")?;
+            for entry in self.not_in_index.iter() {
+                writeln!(f, "  {}", entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fill_report;
+    use crate::{index::Index, Crossword};
+
+    #[test]
+    fn report() {
+        let index = Index::build(vec![
+            (String::from("CAT"), 30),
+            (String::from("ORE"), 10),
+            (String::from("COT"), 20),
+            (String::from("ARE"), 40),
+        ]);
+        let grid = Crossword::from_rows("CAT\nORE\nTE*\n").unwrap();
+
+        let report = fill_report(&index, &grid);
+        assert_eq!(report.word_count, 6);
+        assert_eq!(report.average_length, 16.0 / 6.0);
+        assert_eq!(report.block_count, 1);
+        assert_eq!(report.letter_counts.get(&'T'), Some(&2));
+        assert!(!report.pangram);
+        assert_eq!(report.scrabble_score, 3 + 1 + 1 + 1 + 1 + 1 + 1 + 1);
+        assert_eq!(report.average_weight, Some(25.0));
+        assert_eq!(report.min_weight, Some(10));
+        let lowest: Vec<&str> = report
+            .lowest_weighted
+            .iter()
+            .map(|e| e.word.as_str())
+            .collect();
+        assert_eq!(lowest, vec!["ORE", "COT", "CAT", "ARE"]);
+        let missing: Vec<&str> = report
+            .not_in_index
+            .iter()
+            .map(|e| e.word.as_str())
+            .collect();
+        assert_eq!(missing, vec!["TE", "TE"]);
+    }
+}
@@ -0,0 +1,135 @@
+//! Candidate words for a single entry, checked against its crossings.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crossword::{EntryIterator, EntryLocation},
+    fill::{
+        build_square_to_entry_lookup, cache::FillCache, fill_one_word, filter::AllowedWords,
+        get_orthogonal_words,
+    },
+    index::Index,
+    Crossword,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub word: String,
+    pub weight: i32,
+    /// Whether every crossing can still be completed with the word in place.
+    pub viable: bool,
+    /// Number of words fitting each crossing with the word in place, in the order of the
+    /// entry's cells. Crossings complete before the word was placed count as 1, as the fill
+    /// keeps them as they are.
+    pub crossing_counts: Vec<usize>,
+}
+
+/// Every word fitting `entry_location` in `crossword`, highest weight first, annotated with
+/// how its crossings fare. Returns nothing if the entry is not in the grid.
+pub fn suggest(
+    index: &Index,
+    cache: &mut FillCache,
+    crossword: &Crossword,
+    entry_location: &EntryLocation,
+) -> Vec<Suggestion> {
+    let entry_locations = crossword.get_entries();
+    let entry_location = match entry_locations
+        .iter()
+        .find(|e| e.same_location(entry_location))
+    {
+        Some(entry_location) => entry_location,
+        None => return vec![],
+    };
+    let entry_location_lookup = build_square_to_entry_lookup(&entry_locations);
+    let crossings = get_orthogonal_words(entry_location, &entry_location_lookup);
+
+    let iter = EntryIterator::new(crossword, entry_location);
+    let mut allowed = AllowedWords::default();
+    let words = cache.words.words(iter.clone(), index).clone();
+    let mut order: Vec<usize> = (0..words.len()).collect();
+    allowed.apply(&iter, &words, &mut order);
+
+    order
+        .into_iter()
+        .map(|i| {
+            let (word, weight) = &words[i];
+            let candidate = fill_one_word(crossword, &iter, word);
+            let crossing_counts: Vec<usize> = crossings
+                .iter()
+                .map(|crossing| {
+                    let before = EntryIterator::new(crossword, crossing);
+                    match crossing.prefilled || before.clone().all(|c| c != ' ') {
+                        true => 1,
+                        false => {
+                            allowed.count(&EntryIterator::new(&candidate, crossing), cache, index)
+                        }
+                    }
+                })
+                .collect();
+            Suggestion {
+                word: word.to_string(),
+                weight: *weight,
+                viable: crossing_counts.iter().all(|count| *count > 0),
+                crossing_counts,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::suggest;
+    use crate::{
+        fill::{cache::FillCache, Fill, Filler},
+        index::Index,
+        Crossword,
+    };
+
+    #[test]
+    fn crossing_counts() {
+        let index = Index::build(vec![
+            (String::from("CAT"), 3),
+            (String::from("CUT"), 2),
+            (String::from("COT"), 1),
+            (String::from("CH"), 0),
+            (String::from("AT"), 0),
+            (String::from("AX"), 0),
+            (String::from("OX"), 0),
+            (String::from("TO"), 0),
+        ]);
+        let grid = Crossword::from_string(String::from("      "), 3, 2).unwrap();
+        let entries = grid.get_entries();
+
+        let suggestions = suggest(&index, &mut FillCache::default(), &grid, &entries[0]);
+        let summary: Vec<(&str, bool, Vec<usize>)> = suggestions
+            .iter()
+            .map(|s| (s.word.as_str(), s.viable, s.crossing_counts.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("CAT", true, vec![1, 2, 1]),
+                ("CUT", false, vec![1, 0, 1]),
+                ("COT", true, vec![1, 1, 1]),
+            ]
+        );
+
+        // A prefilled crossing missing from the index is kept as it is, like the fill does.
+        let index = Index::build(
+            ["XAB", "QCD", "ZEF", "ACE", "BDF"]
+                .iter()
+                .map(|w| (String::from(*w), 0))
+                .collect(),
+        );
+        let grid = Crossword::from_string(String::from("X  Q  Z  "), 3, 3).unwrap();
+        let entries = grid.get_entries();
+        let suggestions = suggest(&index, &mut FillCache::default(), &grid, &entries[0]);
+        assert_eq!(suggestions[0].word, "XAB");
+        assert!(suggestions[0].viable);
+        assert_eq!(suggestions[0].crossing_counts[0], 1);
+        assert_eq!(
+            Filler::new(&index).fill(&grid, None).unwrap().contents,
+            "XABQCDZEF"
+        );
+    }
+}
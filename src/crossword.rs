@@ -1,28 +1,215 @@
 use serde::{Deserialize, Serialize};
-use std::{fmt, hash::Hash};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    hash::Hash,
+    sync::Arc,
+};
 
 #[derive(PartialEq, Eq, Debug, Hash, Clone)]
 pub struct Crossword {
     pub(crate) contents: String,
     pub(crate) width: usize,
     pub(crate) height: usize,
+    /// Letters allowed in blank cells, by cell index. Shared between the partial grids of a
+    /// search, which never change it.
+    pub(crate) constraints: Arc<BTreeMap<usize, String>>,
+    /// Cells and entries kept by `clear_unlocked`. Carried over to the grids a fill produces.
+    pub(crate) locks: Arc<Locks>,
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub(crate) struct Locks {
+    cells: BTreeSet<usize>,
+    entries: Vec<EntryLocation>,
 }
 
 impl Crossword {
-    /// Parses a crossword from a string.
+    /// Parses a crossword from a string. Blank cells may be written as a letter set, e.g.
+    /// `[AEIOU]` for a vowel or `[^S]` for anything but S.
     pub fn from_string(s: String, width: usize, height: usize) -> Result<Crossword, String> {
-        let stripped: String = s.chars().filter(|c| *c != '\n').collect();
+        let mut contents = String::with_capacity(width * height);
+        let mut constraints = BTreeMap::new();
+
+        let mut chars = s.chars().filter(|c| *c != '\n');
+        while let Some(c) = chars.next() {
+            if c != '[' {
+                contents.push(c);
+                continue;
+            }
+            let mut set = String::new();
+            let mut closed = false;
+            for c in chars.by_ref() {
+                if c == ']' {
+                    closed = true;
+                    break;
+                }
+                set.push(c);
+            }
+            if !closed {
+                return Err(String::from("Unclosed letter set."));
+            }
+            constraints.insert(contents.len(), parse_letter_set(&set)?);
+            contents.push(' ');
+        }
 
-        if width * height != stripped.len() {
+        if width * height != contents.len() {
             return Err(String::from("Input dimensions do not match."));
         }
         Ok(Crossword {
-            contents: stripped,
+            contents,
             width,
             height,
+            constraints: Arc::new(constraints),
+            locks: Arc::default(),
         })
     }
 
+    /// Overwrites the cell at `index` in `contents`.
+    pub(crate) fn set_cell(&mut self, index: usize, c: u8) {
+        let mut buf = [0; 4];
+        self.contents
+            .replace_range(index..=index, char::from(c).encode_utf8(&mut buf));
+    }
+
+    pub fn lock_cell(&mut self, row: usize, col: usize) -> Result<(), String> {
+        if row >= self.height || col >= self.width {
+            return Err(format!("No cell at {}, {}.", row, col));
+        }
+        let cell = row * self.width + col;
+        Arc::make_mut(&mut self.locks).cells.insert(cell);
+        Ok(())
+    }
+
+    pub fn unlock_cell(&mut self, row: usize, col: usize) -> Result<(), String> {
+        if row >= self.height || col >= self.width {
+            return Err(format!("No cell at {}, {}.", row, col));
+        }
+        let cell = row * self.width + col;
+        Arc::make_mut(&mut self.locks).cells.remove(&cell);
+        Ok(())
+    }
+
+    /// Locks every cell of `entry_location`. Unlocking a crossing entry later leaves the
+    /// shared cell locked.
+    pub fn lock_entry(&mut self, entry_location: &EntryLocation) {
+        if !self.is_entry_locked(entry_location) {
+            Arc::make_mut(&mut self.locks)
+                .entries
+                .push(entry_location.clone());
+        }
+    }
+
+    pub fn unlock_entry(&mut self, entry_location: &EntryLocation) {
+        Arc::make_mut(&mut self.locks)
+            .entries
+            .retain(|e| !e.same_location(entry_location));
+    }
+
+    pub fn is_entry_locked(&self, entry_location: &EntryLocation) -> bool {
+        self.locks
+            .entries
+            .iter()
+            .any(|e| e.same_location(entry_location))
+    }
+
+    /// Whether the cell is locked, by itself or as part of a locked entry.
+    pub fn is_cell_locked(&self, row: usize, col: usize) -> bool {
+        self.locks.cells.contains(&(row * self.width + col))
+            || self
+                .locks
+                .entries
+                .iter()
+                .any(|e| e.cells().any(|cell| cell == (row, col)))
+    }
+
+    /// Blanks every letter outside the locked cells and entries, keeping blocks, letter sets
+    /// and the locks themselves.
+    pub fn clear_unlocked(&mut self) {
+        let contents: String = self
+            .contents
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match c {
+                '*' => c,
+                _ if self.is_cell_locked(i / self.width, i % self.width) => c,
+                _ => ' ',
+            })
+            .collect();
+        self.contents = contents;
+    }
+
+    /// Parses a crossword written one row per line, as printed by `Display`. Short rows are
+    /// padded with blank cells, since editors tend to strip trailing spaces.
+    pub fn from_rows(s: &str) -> Result<Crossword, String> {
+        let rows: Vec<&str> = s
+            .lines()
+            .map(|row| row.trim_end_matches('\r'))
+            .filter(|row| !row.is_empty())
+            .collect();
+        let width = rows.iter().map(|row| cell_count(row)).max().unwrap_or(0);
+
+        let mut contents = String::new();
+        for row in rows.iter() {
+            contents.push_str(row);
+            contents.extend(std::iter::repeat_n(' ', width - cell_count(row)));
+        }
+        Crossword::from_string(contents, width, rows.len())
+    }
+
+    /// Limits the cell at `row`, `col` to `letters` while it is blank. An empty string removes
+    /// the limit, while a set allowing no letter is an error.
+    pub fn set_cell_constraint(
+        &mut self,
+        row: usize,
+        col: usize,
+        letters: &str,
+    ) -> Result<(), String> {
+        if row >= self.height || col >= self.width {
+            return Err(format!("No cell at {}, {}.", row, col));
+        }
+        let cell = row * self.width + col;
+        if self.contents.as_bytes()[cell] == b'*' {
+            return Err(format!("Cell {}, {} is a block.", row, col));
+        }
+
+        let constraints = Arc::make_mut(&mut self.constraints);
+        match letters.trim().is_empty() {
+            true => constraints.remove(&cell),
+            false => constraints.insert(cell, parse_letter_set(letters)?),
+        };
+        Ok(())
+    }
+
+    /// The letters allowed in the cell at `row`, `col`, if they are limited.
+    pub fn cell_constraint(&self, row: usize, col: usize) -> Option<&str> {
+        self.constraints
+            .get(&(row * self.width + col))
+            .map(String::as_str)
+    }
+
+    /// The grid as accepted by `from_string`, without line breaks.
+    pub fn to_grid_string(&self) -> String {
+        (0..self.height).map(|row| self.row_string(row)).collect()
+    }
+
+    fn row_string(&self, row: usize) -> String {
+        let mut result = String::with_capacity(self.width);
+        for col in 0..self.width {
+            let cell = row * self.width + col;
+            let c = self.contents.as_bytes()[cell] as char;
+            match self.constraints.get(&cell) {
+                Some(letters) if c == ' ' => {
+                    result.push('[');
+                    result.push_str(letters);
+                    result.push(']');
+                }
+                _ => result.push(c),
+            }
+        }
+        result
+    }
+
     pub fn get_entries(&self) -> Vec<EntryLocation> {
         let mut result = vec![];
 
@@ -38,14 +225,14 @@ impl Crossword {
                 let c = bytes[row * self.width + col] as char;
                 if c != '*' {
                     // White square / letter found.
-                    if start_row == None {
+                    if start_row.is_none() {
                         start_row = Some(row);
                         start_col = Some(col);
                     }
                     length += 1;
                     prefilled &= c != ' ';
                 } else {
-                    if start_row == None {
+                    if start_row.is_none() {
                         continue;
                     }
 
@@ -91,14 +278,14 @@ impl Crossword {
                 let c = bytes[row * self.width + col] as char;
                 if c != '*' {
                     // White square / letter found.
-                    if start_row == None {
+                    if start_row.is_none() {
                         start_row = Some(row);
                         start_col = Some(col);
                     }
                     length += 1;
                     prefilled &= c != ' ';
                 } else {
-                    if start_row == None {
+                    if start_row.is_none() {
                         continue;
                     }
 
@@ -141,19 +328,54 @@ impl Crossword {
 impl fmt::Display for Crossword {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for row in 0..self.height {
-            for col in 0..self.width {
-                write!(
-                    f,
-                    "{}",
-                    self.contents.as_bytes()[row * self.width + col] as char
-                )?;
-            }
-            writeln!(f)?;
+            writeln!(f, "{}", self.row_string(row))?;
         }
         Ok(())
     }
 }
 
+/// Number of cells in a row, counting each letter set as one.
+fn cell_count(row: &str) -> usize {
+    let mut count = 0;
+    let mut in_set = false;
+    for c in row.chars() {
+        match c {
+            '[' => in_set = true,
+            ']' => {
+                in_set = false;
+                count += 1;
+            }
+            _ if !in_set => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+/// Parses the inside of a letter set, e.g. `AEIOU` or `^S`, into sorted uppercase letters.
+/// Sets allowing no letter are an error.
+fn parse_letter_set(set: &str) -> Result<String, String> {
+    let (negated, letters) = match set.strip_prefix(['^', '!']) {
+        Some(rest) => (true, rest),
+        None => (false, set),
+    };
+    let mut result = [false; 26];
+    for c in letters.chars().filter(|c| !c.is_whitespace() && *c != '/') {
+        if !c.is_ascii_alphabetic() {
+            return Err(format!("Unexpected '{}' in letter set.", c));
+        }
+        result[(c.to_ascii_uppercase() as u8 - b'A') as usize] = true;
+    }
+    let letters: String = (b'A'..=b'Z')
+        .filter(|c| result[(c - b'A') as usize] != negated)
+        .map(|c| c as char)
+        .collect();
+    match letters.is_empty() {
+        true => Err(format!("Letter set [{}] allows no letter.", set)),
+        false => Ok(letters),
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Across,
@@ -161,7 +383,7 @@ pub enum Direction {
 }
 
 /// A word location in a `Crossword`.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct EntryLocation {
     pub(crate) start_row: usize,
     pub(crate) start_col: usize,
@@ -186,6 +408,21 @@ impl EntryLocation {
             prefilled,
         }
     }
+
+    /// Whether both describe the entry starting at the same cell in the same direction.
+    pub fn same_location(&self, other: &EntryLocation) -> bool {
+        self.direction == other.direction
+            && self.start_row == other.start_row
+            && self.start_col == other.start_col
+    }
+
+    /// The (row, column) of each of the entry's cells, in order.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.length).map(move |i| match self.direction {
+            Direction::Across => (self.start_row, self.start_col + i),
+            Direction::Down => (self.start_row + i, self.start_col),
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -205,6 +442,43 @@ impl<'s> EntryIterator<'s> {
     }
 }
 
+impl<'s> EntryIterator<'s> {
+    /// The index in the grid of the entry's `i`th cell.
+    fn cell(&self, i: usize) -> usize {
+        match self.entry_location.direction {
+            Direction::Across => {
+                self.entry_location.start_row * self.crossword.width
+                    + self.entry_location.start_col
+                    + i
+            }
+            Direction::Down => {
+                (self.entry_location.start_row + i) * self.crossword.width
+                    + self.entry_location.start_col
+            }
+        }
+    }
+
+    /// The letter sets of the entry's blank cells, by position in the entry.
+    pub(crate) fn blank_constraints(&self) -> Vec<(usize, &'s str)> {
+        if self.crossword.constraints.is_empty() {
+            return vec![];
+        }
+        (0..self.entry_location.length)
+            .filter_map(|i| {
+                let cell = self.cell(i);
+                match self.crossword.contents.as_bytes()[cell] {
+                    b' ' => self
+                        .crossword
+                        .constraints
+                        .get(&cell)
+                        .map(|letters| (i, letters.as_str())),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
 impl<'s> Iterator for EntryIterator<'s> {
     type Item = char;
 
@@ -213,17 +487,7 @@ impl<'s> Iterator for EntryIterator<'s> {
             return None;
         }
 
-        let char_index = match self.entry_location.direction {
-            Direction::Across => {
-                self.entry_location.start_row * self.crossword.width
-                    + self.entry_location.start_col
-                    + self.index
-            }
-            Direction::Down => {
-                (self.entry_location.start_row + self.index) * self.crossword.width
-                    + self.entry_location.start_col
-            }
-        };
+        let char_index = self.cell(self.index);
         self.index += 1;
         let result = self.crossword.contents.as_bytes()[char_index] as char;
         Some(result)
@@ -253,7 +517,7 @@ mod tests {
         let entries = c.get_entries();
 
         assert_eq!(entries.len(), 12);
-        assert_eq!(entries.last().unwrap().prefilled, false);
+        assert!(!entries.last().unwrap().prefilled);
     }
 
     #[test]
@@ -270,4 +534,52 @@ mod tests {
         let iter = EntryIterator::new(&c, entry_location);
         assert_eq!(String::from("ABCDE"), iter.collect::<String>());
     }
+
+    #[test]
+    fn letter_sets() {
+        let mut c = Crossword::from_string(
+            String::from("A[aeiou] \n[^ABCDEFGHIJKLMNOPQRSTUVW]**"),
+            3,
+            2,
+        )
+        .unwrap();
+        assert_eq!(c.contents, "A   **");
+        assert_eq!(c.cell_constraint(0, 1), Some("AEIOU"));
+        assert_eq!(c.cell_constraint(1, 0), Some("XYZ"));
+
+        c.set_cell_constraint(0, 2, "s/t").unwrap();
+        assert!(c.set_cell_constraint(1, 1, "S").is_err());
+        assert!(c
+            .set_cell_constraint(0, 2, "^ABCDEFGHIJKLMNOPQRSTUVWXYZ")
+            .is_err());
+        assert!(Crossword::from_string(String::from("A[] "), 3, 1).is_err());
+        assert_eq!(c.to_grid_string(), "A[AEIOU][ST][XYZ]**");
+        assert_eq!(Crossword::from_string(c.to_grid_string(), 3, 2).unwrap(), c);
+
+        let entries = c.get_entries();
+        let iter = EntryIterator::new(&c, &entries[0]);
+        assert_eq!(iter.blank_constraints(), vec![(1, "AEIOU"), (2, "ST")]);
+
+        c.set_cell_constraint(0, 2, "").unwrap();
+        assert_eq!(c.cell_constraint(0, 2), None);
+    }
+
+    #[test]
+    fn clear_unlocked() {
+        let mut c = Crossword::from_string(String::from("CATORETEA"), 3, 3).unwrap();
+        let entries = c.get_entries();
+        // CAT across and COT down.
+        c.lock_entry(&entries[0]);
+        c.lock_entry(&entries[3]);
+        c.lock_cell(2, 2).unwrap();
+        assert!(c.lock_cell(0, 3).is_err());
+        assert!(c.unlock_cell(3, 0).is_err());
+
+        c.unlock_entry(&entries[0]);
+        assert!(c.is_cell_locked(0, 0));
+        assert!(!c.is_cell_locked(0, 1));
+
+        c.clear_unlocked();
+        assert_eq!(c.contents, "C  O  T A");
+    }
 }
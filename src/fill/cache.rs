@@ -1,59 +1,313 @@
-use rustc_hash::{FxHashMap, FxHasher};
-use std::hash::{Hash, Hasher};
+use rustc_hash::FxHashMap;
+use std::{mem, sync::Arc};
 
 use crate::index::Index;
 
+/// Default memory budget for a `FillCache`, in bytes.
+pub const DEFAULT_CACHE_BYTES: usize = 64 << 20;
+
+/// Rough per-entry bookkeeping cost of a `BoundedCache`, in bytes.
+const ENTRY_OVERHEAD: usize = 48;
+
+/// A pattern-keyed cache that evicts its least recently used entries once its estimated
+/// size exceeds `capacity_bytes`.
+#[derive(Debug, Clone)]
+pub struct BoundedCache<V> {
+    entries: FxHashMap<String, CacheEntry<V>>,
+    capacity_bytes: usize,
+    size_bytes: usize,
+    clock: u64,
+    key: String,
+    /// The last value too large to ever fit, kept only until the next lookup.
+    oversized: Option<V>,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry<V> {
+    value: V,
+    size_bytes: usize,
+    last_used: u64,
+}
+
+impl<V> BoundedCache<V> {
+    pub fn with_capacity(capacity_bytes: usize) -> BoundedCache<V> {
+        BoundedCache {
+            entries: FxHashMap::default(),
+            capacity_bytes,
+            size_bytes: 0,
+            clock: 0,
+            key: String::new(),
+            oversized: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Estimated memory used by the cached entries, in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity_bytes
+    }
+
+    pub fn set_capacity_bytes(&mut self, capacity_bytes: usize) {
+        self.capacity_bytes = capacity_bytes;
+        self.evict(0);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size_bytes = 0;
+        self.oversized = None;
+    }
+
+    /// Whether `pattern` is cached, without counting as a use.
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.entries.contains_key(pattern)
+    }
+
+    /// Looks up the entry for `pattern`, computing it with `compute` on a miss.
+    /// `size_of` estimates the heap size of a value. Values larger than the whole capacity
+    /// are returned without being cached.
+    pub fn get_or_insert_with<T, F, S>(&mut self, pattern: T, compute: F, size_of: S) -> &V
+    where
+        T: Iterator<Item = char> + Clone,
+        F: FnOnce(T) -> V,
+        S: FnOnce(&V) -> usize,
+    {
+        self.clock += 1;
+        self.oversized = None;
+
+        let mut key = mem::take(&mut self.key);
+        key.clear();
+        key.extend(pattern.clone());
+
+        if !self.entries.contains_key(key.as_str()) {
+            let value = compute(pattern);
+            let size_bytes = ENTRY_OVERHEAD + key.len() + size_of(&value);
+            if size_bytes > self.capacity_bytes {
+                self.key = key;
+                return self.oversized.insert(value);
+            }
+            self.evict(size_bytes);
+            self.size_bytes += size_bytes;
+            self.entries.insert(
+                key.clone(),
+                CacheEntry {
+                    value,
+                    size_bytes,
+                    last_used: 0,
+                },
+            );
+        }
+
+        let entry = self.entries.get_mut(key.as_str()).unwrap();
+        entry.last_used = self.clock;
+        self.key = key;
+        &entry.value
+    }
+
+    /// Drops least recently used entries until `incoming` more bytes fit, leaving some
+    /// headroom so eviction does not run on every miss.
+    fn evict(&mut self, incoming: usize) {
+        if self.size_bytes + incoming <= self.capacity_bytes {
+            return;
+        }
+
+        let target = (self.capacity_bytes / 4 * 3).saturating_sub(incoming);
+        let mut by_age: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort_unstable();
+
+        for (_, key) in by_age {
+            if self.size_bytes <= target {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                self.size_bytes -= entry.size_bytes;
+            }
+        }
+    }
+}
+
+/// The weighted words matching a pattern. Shared rather than copied by everything that
+/// reads them, so a search can keep one alive while it tries its words.
+pub type WordList = Arc<[(Box<str>, i32)]>;
+
 #[derive(Clone)]
 pub struct CachedWords {
-    words_cache: FxHashMap<u64, Vec<String>>,
+    words_cache: BoundedCache<WordList>,
+}
+
+impl Default for CachedWords {
+    fn default() -> CachedWords {
+        CachedWords::with_capacity(DEFAULT_CACHE_BYTES / 5 * 4)
+    }
 }
 
 impl CachedWords {
-    pub fn default() -> CachedWords {
+    pub fn with_capacity(capacity_bytes: usize) -> CachedWords {
         CachedWords {
-            words_cache: FxHashMap::default(),
+            words_cache: BoundedCache::with_capacity(capacity_bytes),
         }
     }
 
+    /// Returns the weighted words matching `pattern`, in the order of `Index::weighted_words`.
     pub fn words<T: Iterator<Item = char> + Clone>(
         &mut self,
         pattern: T,
         index: &Index,
-    ) -> &Vec<String> {
-        let mut hasher = FxHasher::default();
-        for c in pattern.clone() {
-            c.hash(&mut hasher);
-        }
-        let key = hasher.finish();
+    ) -> &WordList {
+        self.words_cache.get_or_insert_with(
+            pattern,
+            |pattern| {
+                index
+                    .weighted_words(pattern)
+                    .into_iter()
+                    .map(|(word, weight)| (word.into_boxed_str(), weight))
+                    .collect()
+            },
+            |words| {
+                words
+                    .iter()
+                    .map(|w| w.0.len() + mem::size_of::<(Box<str>, i32)>())
+                    .sum()
+            },
+        )
+    }
+
+    pub fn cache(&self) -> &BoundedCache<WordList> {
+        &self.words_cache
+    }
 
-        self.words_cache
-            .entry(key)
-            .or_insert_with(|| index.words(pattern))
+    pub fn cache_mut(&mut self) -> &mut BoundedCache<WordList> {
+        &mut self.words_cache
     }
 }
 
 #[derive(Clone)]
 pub struct CachedIsValid {
-    is_valid_cache: FxHashMap<u64, bool>,
+    is_valid_cache: BoundedCache<bool>,
+}
+
+impl Default for CachedIsValid {
+    fn default() -> CachedIsValid {
+        CachedIsValid::with_capacity(DEFAULT_CACHE_BYTES / 5)
+    }
 }
 
 impl CachedIsValid {
-    pub fn default() -> CachedIsValid {
+    pub fn with_capacity(capacity_bytes: usize) -> CachedIsValid {
         CachedIsValid {
-            is_valid_cache: FxHashMap::default(),
+            is_valid_cache: BoundedCache::with_capacity(capacity_bytes),
         }
     }
 
     pub fn is_valid<T: Iterator<Item = char> + Clone>(&mut self, chars: T, index: &Index) -> bool {
-        let mut hasher = FxHasher::default();
-        for c in chars.clone() {
-            c.hash(&mut hasher);
-        }
-        let key = hasher.finish();
-
         *self
             .is_valid_cache
-            .entry(key)
-            .or_insert_with(|| index.is_valid(chars))
+            .get_or_insert_with(chars, |chars| index.is_valid(chars), |_| 0)
+    }
+
+    pub fn cache(&self) -> &BoundedCache<bool> {
+        &self.is_valid_cache
+    }
+
+    pub fn cache_mut(&mut self) -> &mut BoundedCache<bool> {
+        &mut self.is_valid_cache
+    }
+}
+
+/// The word and validity caches used by a `Filler`. Entries depend only on the `Index`, so a
+/// cache can be handed from one fill to the next as long as the index stays the same.
+#[derive(Clone, Default)]
+pub struct FillCache {
+    pub words: CachedWords,
+    pub is_valid: CachedIsValid,
+}
+
+impl FillCache {
+    /// Creates caches sharing a total memory budget of `capacity_bytes`.
+    pub fn with_capacity(capacity_bytes: usize) -> FillCache {
+        FillCache {
+            words: CachedWords::with_capacity(capacity_bytes / 5 * 4),
+            is_valid: CachedIsValid::with_capacity(capacity_bytes / 5),
+        }
+    }
+
+    pub fn set_capacity_bytes(&mut self, capacity_bytes: usize) {
+        self.words
+            .cache_mut()
+            .set_capacity_bytes(capacity_bytes / 5 * 4);
+        self.is_valid
+            .cache_mut()
+            .set_capacity_bytes(capacity_bytes / 5);
+    }
+
+    pub fn clear(&mut self) {
+        self.words.cache_mut().clear();
+        self.is_valid.cache_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoundedCache;
+
+    #[test]
+    fn bounded_cache_evicts_least_recently_used() {
+        let mut cache = BoundedCache::with_capacity(300);
+
+        for pattern in &["AB", "CD", "EF"] {
+            cache.get_or_insert_with(pattern.chars(), |p| p.collect::<String>(), |_| 40);
+        }
+        // Touch the oldest entry so it survives the next eviction.
+        cache.get_or_insert_with("AB".chars(), |_| unreachable!(), |_| 40);
+        cache.get_or_insert_with("GH".chars(), |p| p.collect::<String>(), |_| 40);
+
+        assert!(cache.size_bytes() <= cache.capacity_bytes());
+        assert_eq!(
+            cache.get_or_insert_with("AB".chars(), |_| unreachable!(), |_| 0),
+            "AB"
+        );
+        assert_eq!(
+            cache.get_or_insert_with("CD".chars(), |_| String::from("recomputed"), |_| 0),
+            "recomputed"
+        );
+    }
+
+    #[test]
+    fn bounded_cache_skips_oversized_values() {
+        let mut cache = BoundedCache::with_capacity(100);
+
+        cache.get_or_insert_with("AB".chars(), |p| p.collect::<String>(), |_| 40);
+        assert_eq!(
+            cache.get_or_insert_with("CD".chars(), |_| String::from("large"), |_| 500),
+            "large"
+        );
+        assert_eq!(cache.len(), 1);
+        assert!(cache.size_bytes() <= cache.capacity_bytes());
+    }
+
+    #[test]
+    fn bounded_cache_keys_are_exact() {
+        let mut cache = BoundedCache::with_capacity(1 << 20);
+
+        let first = *cache.get_or_insert_with("A B".chars(), |_| 1, |_| 0);
+        let second = *cache.get_or_insert_with("AB ".chars(), |_| 2, |_| 0);
+
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(cache.len(), 2);
     }
 }
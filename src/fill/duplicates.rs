@@ -0,0 +1,226 @@
+//! Grid-wide duplicate detection.
+//!
+//! Entries that are already complete in a partial grid never change again, so each search
+//! node records them once and only the entries completed by the next word need checking.
+
+use rustc_hash::FxHashSet;
+
+use super::FillOptions;
+use crate::{
+    crossword::{Direction, EntryIterator, EntryLocation},
+    Crossword,
+};
+
+/// How alike two entries may be.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DuplicateRules {
+    /// Rejects entries containing another entry, e.g. RUN and RERUN.
+    pub forbid_contained: bool,
+    /// Rejects entries sharing their first this many letters, e.g. 4 for TRAIN and TRAINER.
+    /// A plain prefix rule: words are not stemmed, so small lengths catch unrelated words too.
+    pub shared_prefix_length: Option<usize>,
+}
+
+impl DuplicateRules {
+    pub fn new(options: &FillOptions) -> DuplicateRules {
+        DuplicateRules {
+            forbid_contained: options.forbid_contained_entries,
+            shared_prefix_length: options.shared_prefix_length.filter(|n| *n > 0),
+        }
+    }
+
+    fn is_strict(&self) -> bool {
+        self.forbid_contained || self.shared_prefix_length.is_some()
+    }
+
+    /// Whether `a` and `b` may not both appear in a grid.
+    pub fn clash(&self, a: &str, b: &str) -> bool {
+        if a == b {
+            return true;
+        }
+        if self.forbid_contained && (a.contains(b) || b.contains(a)) {
+            return true;
+        }
+        match self.shared_prefix_length {
+            Some(n) => a.len() >= n && b.len() >= n && a[..n] == b[..n],
+            None => false,
+        }
+    }
+}
+
+/// The complete entries of the grid a search is at. Entries completed by a placement are
+/// added as it is made and removed when it is undone, like the cells of a `TrailGrid`.
+pub struct UsedEntries {
+    words: FxHashSet<String>,
+    locations: FxHashSet<(Direction, usize, usize)>,
+    /// Entries added since `new`, latest last.
+    added: Vec<((Direction, usize, usize), String)>,
+    /// Words checked since `start_check`, back to back, and where each of them ends.
+    pending: String,
+    pending_ends: Vec<usize>,
+}
+
+impl UsedEntries {
+    pub fn new(candidate: &Crossword, entry_locations: &[EntryLocation]) -> UsedEntries {
+        let mut used = UsedEntries {
+            words: FxHashSet::default(),
+            locations: FxHashSet::default(),
+            added: vec![],
+            pending: String::new(),
+            pending_ends: vec![],
+        };
+        for entry_location in entry_locations {
+            let iter = EntryIterator::new(candidate, entry_location);
+            if iter.clone().all(|c| c != ' ') {
+                used.words.insert(iter.collect());
+                used.locations.insert(location_key(entry_location));
+            }
+        }
+        used
+    }
+
+    /// The point to `undo` back to in order to drop the entries added after this call.
+    pub fn mark(&self) -> usize {
+        self.added.len()
+    }
+
+    /// Adds the entries among `entry_locations` that are complete in `candidate`.
+    pub fn add_complete<'e>(
+        &mut self,
+        candidate: &Crossword,
+        entry_locations: impl IntoIterator<Item = &'e EntryLocation>,
+    ) {
+        for entry_location in entry_locations {
+            let iter = EntryIterator::new(candidate, entry_location);
+            let location = location_key(entry_location);
+            if !self.locations.contains(&location) && iter.clone().all(|c| c != ' ') {
+                // Added entries were checked not to clash, so none of them repeats a word.
+                let word: String = iter.collect();
+                self.words.insert(word.clone());
+                self.locations.insert(location.clone());
+                self.added.push((location, word));
+            }
+        }
+    }
+
+    /// Removes the entries added since `mark`.
+    pub fn undo(&mut self, mark: usize) {
+        for (location, word) in self.added.drain(mark..) {
+            self.locations.remove(&location);
+            self.words.remove(&word);
+        }
+    }
+
+    /// Whether `entry_location` was already complete, and so cannot have changed.
+    pub fn contains_location(&self, entry_location: &EntryLocation) -> bool {
+        self.locations.contains(&location_key(entry_location))
+    }
+
+    /// Whether `word` clashes with a complete entry.
+    pub fn clashes(&self, word: &str, rules: &DuplicateRules) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+        rules.is_strict() && self.words.iter().any(|used| rules.clash(used, word))
+    }
+
+    /// Starts checking the entries completed by a new placement with `check`.
+    pub fn start_check(&mut self) {
+        self.pending.clear();
+        self.pending_ends.clear();
+    }
+
+    /// Reads the complete entry under `iter` and returns its word, unless it clashes with a
+    /// complete entry or with another word checked since `start_check`.
+    pub fn check(&mut self, iter: EntryIterator, rules: &DuplicateRules) -> Option<&str> {
+        let start = self.pending.len();
+        self.pending.extend(iter);
+        let word = &self.pending[start..];
+
+        let mut previous = 0;
+        let clashes = self.clashes(word, rules)
+            || self.pending_ends.iter().any(|end| {
+                let checked = &self.pending[previous..*end];
+                previous = *end;
+                rules.clash(checked, word)
+            });
+        if clashes {
+            return None;
+        }
+        self.pending_ends.push(self.pending.len());
+        Some(&self.pending[start..])
+    }
+}
+
+fn location_key(entry_location: &EntryLocation) -> (Direction, usize, usize) {
+    (
+        entry_location.direction.clone(),
+        entry_location.start_row,
+        entry_location.start_col,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateRules, UsedEntries};
+    use crate::{crossword::EntryIterator, fill::trail::TrailGrid, Crossword};
+
+    #[test]
+    fn rules() {
+        let plain = DuplicateRules::default();
+        assert!(plain.clash("RUN", "RUN"));
+        assert!(!plain.clash("RUN", "RERUN"));
+
+        let strict = DuplicateRules {
+            forbid_contained: true,
+            shared_prefix_length: Some(4),
+        };
+        assert!(strict.clash("RUN", "RERUN"));
+        assert!(strict.clash("TRAIN", "TRAINER"));
+        assert!(!strict.clash("STAR", "SPAR"));
+
+        // Entries shorter than the prefix never share it.
+        let prefixes = DuplicateRules {
+            forbid_contained: false,
+            shared_prefix_length: Some(4),
+        };
+        assert!(!prefixes.clash("TRA", "TRAP"));
+    }
+
+    #[test]
+    fn used_entries_follow_placements() {
+        let grid = Crossword::from_string(String::from("CAT      "), 3, 3).unwrap();
+        let entries = grid.get_entries();
+        let rules = DuplicateRules::default();
+        let mut used = UsedEntries::new(&grid, &entries);
+        let mut trail = TrailGrid::new(grid);
+
+        let mark = used.mark();
+        trail.place(&entries[1], "ORE");
+        used.add_complete(trail.grid(), &entries);
+        assert!(used.contains_location(&entries[1]));
+        assert!(used.clashes("ORE", &rules));
+
+        // Words checked together must not clash with each other either.
+        used.start_check();
+        assert_eq!(
+            used.check(EntryIterator::new(trail.grid(), &entries[0]), &rules),
+            None
+        );
+        used.start_check();
+        let row = Crossword::from_string(String::from("TEETEE   "), 3, 3).unwrap();
+        assert_eq!(
+            used.check(EntryIterator::new(&row, &entries[0]), &rules),
+            Some("TEE")
+        );
+        assert_eq!(
+            used.check(EntryIterator::new(&row, &entries[1]), &rules),
+            None
+        );
+
+        used.undo(mark);
+        assert!(!used.contains_location(&entries[1]));
+        assert!(!used.clashes("ORE", &rules));
+        assert!(used.clashes("CAT", &rules));
+    }
+}
@@ -0,0 +1,277 @@
+//! Per-fill word exclusions and preferences, and per-cell letter sets, applied on top of the
+//! shared index and cache.
+
+use regex::{Regex, RegexBuilder};
+use rustc_hash::FxHashSet;
+
+use super::{
+    cache::{BoundedCache, FillCache, DEFAULT_CACHE_BYTES},
+    FillOptions,
+};
+use crate::{
+    crossword::EntryIterator,
+    index::{normalize_entry, Index},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    excluded: FxHashSet<String>,
+    excluded_patterns: Vec<Regex>,
+    min_weight: Option<i32>,
+    desired: FxHashSet<String>,
+}
+
+impl WordFilter {
+    /// Builds the filter described by `options`, failing if an excluded pattern is not a valid
+    /// regular expression.
+    pub fn new(options: &FillOptions) -> Result<WordFilter, String> {
+        let excluded_patterns = options
+            .excluded_patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(&format!("^(?:{})$", pattern))
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("Invalid excluded pattern {}: {}", pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(WordFilter {
+            excluded: options
+                .excluded_words
+                .iter()
+                .map(|w| normalize_entry(w))
+                .collect(),
+            excluded_patterns,
+            min_weight: options.min_weight,
+            desired: options
+                .desired_words
+                .iter()
+                .map(|w| normalize_entry(w))
+                .collect(),
+        })
+    }
+
+    /// Whether the filter excludes nothing, so word lists can be used as they are.
+    pub fn excludes_nothing(&self) -> bool {
+        self.min_weight.is_none() && !self.excludes_words()
+    }
+
+    /// Whether words are excluded other than by weight.
+    fn excludes_words(&self) -> bool {
+        !self.excluded.is_empty() || !self.excluded_patterns.is_empty()
+    }
+
+    pub fn allows(&self, word: &str, weight: i32) -> bool {
+        self.min_weight.is_none_or(|min| weight >= min)
+            && !self.excluded.contains(word)
+            && !self.excluded_patterns.iter().any(|r| r.is_match(word))
+    }
+
+    /// Like `allows`, looking the weight up in `index` only if it matters.
+    pub fn allows_entry(&self, word: &str, index: &Index) -> bool {
+        let weight = match self.min_weight {
+            Some(_) => index.get(word).map_or(i32::MIN, |w| w.weight),
+            None => 0,
+        };
+        self.allows(word, weight)
+    }
+
+    pub fn is_desired(&self, word: &str) -> bool {
+        self.desired.contains(word)
+    }
+
+    /// Drops excluded words from `order`, a list of positions in `words`, and moves desired
+    /// ones to the front, otherwise keeping their order.
+    pub fn apply(&self, words: &[(Box<str>, i32)], order: &mut Vec<usize>) {
+        if !self.excludes_nothing() {
+            order.retain(|i| self.allows(&words[*i].0, words[*i].1));
+        }
+        self.prefer_desired(words, order);
+    }
+
+    /// Moves desired words to the front of `order`, otherwise keeping their order.
+    pub fn prefer_desired(&self, words: &[(Box<str>, i32)], order: &mut [usize]) {
+        if !self.desired.is_empty() {
+            order.sort_by_key(|i| !self.is_desired(&words[*i].0));
+        }
+    }
+}
+
+/// Whether `word` has an allowed letter at each constrained position.
+fn satisfies(word: &str, constraints: &[(usize, &str)]) -> bool {
+    let bytes = word.as_bytes();
+    constraints
+        .iter()
+        .all(|(i, letters)| letters.as_bytes().contains(&bytes[*i]))
+}
+
+/// Memory budget for the per-search counts of `AllowedWords`, in bytes.
+const COUNTS_CACHE_BYTES: usize = DEFAULT_CACHE_BYTES / 16;
+
+/// The words an entry may take given a `WordFilter` and the letter sets of its blank cells.
+/// Counts are remembered per pattern for the length of a search, since they cannot be kept
+/// in the shared cache.
+#[derive(Debug, Clone)]
+pub struct AllowedWords {
+    pub filter: WordFilter,
+    counts: BoundedCache<usize>,
+    weighted_counts: BoundedCache<u64>,
+    /// Whether a pattern can be completed at the minimum weight, for `any`.
+    valid: BoundedCache<bool>,
+    /// The index's lowest weight, for `weighted_count`.
+    weight_floor: Option<i32>,
+}
+
+impl Default for AllowedWords {
+    fn default() -> AllowedWords {
+        AllowedWords::new(WordFilter::default())
+    }
+}
+
+impl AllowedWords {
+    pub fn new(filter: WordFilter) -> AllowedWords {
+        AllowedWords {
+            filter,
+            counts: BoundedCache::with_capacity(COUNTS_CACHE_BYTES),
+            weighted_counts: BoundedCache::with_capacity(COUNTS_CACHE_BYTES),
+            valid: BoundedCache::with_capacity(COUNTS_CACHE_BYTES),
+            weight_floor: None,
+        }
+    }
+
+    /// Sets the lowest weight `weighted_count` measures from. Finding it walks the whole
+    /// index, so searches set it once up front; otherwise the first `weighted_count` does.
+    pub fn set_weight_floor(&mut self, index: &Index) {
+        self.weight_floor = Some(index.min_weight().unwrap_or(0));
+    }
+
+    /// Number of words the entry under `iter` may take.
+    pub fn count(&mut self, iter: &EntryIterator, cache: &mut FillCache, index: &Index) -> usize {
+        let constraints = iter.blank_constraints();
+        if self.filter.excludes_nothing() && constraints.is_empty() {
+            return cache.words.words(iter.clone(), index).len();
+        }
+
+        let key = constrained_pattern(iter, &constraints);
+        let filter = &self.filter;
+        *self.counts.get_or_insert_with(
+            key.chars(),
+            |_| {
+                cache
+                    .words
+                    .words(iter.clone(), index)
+                    .iter()
+                    .filter(|(word, weight)| {
+                        filter.allows(word, *weight) && satisfies(word, &constraints)
+                    })
+                    .count()
+            },
+            |_| 0,
+        )
+    }
+
+    /// Like `count`, but each word counts one more than its weight is above the index's
+    /// lowest, so an entry left with only poor words scores low.
+    pub fn weighted_count(
+        &mut self,
+        iter: &EntryIterator,
+        cache: &mut FillCache,
+        index: &Index,
+    ) -> u64 {
+        let constraints = iter.blank_constraints();
+        let key = constrained_pattern(iter, &constraints);
+        if self.weight_floor.is_none() {
+            self.set_weight_floor(index);
+        }
+        let floor = i64::from(self.weight_floor.unwrap_or(0));
+        let filter = &self.filter;
+        *self.weighted_counts.get_or_insert_with(
+            key.chars(),
+            |_| {
+                cache
+                    .words
+                    .words(iter.clone(), index)
+                    .iter()
+                    .filter(|(word, weight)| {
+                        filter.allows(word, *weight) && satisfies(word, &constraints)
+                    })
+                    .map(|(_, weight)| (i64::from(*weight) - floor).max(0) as u64 + 1)
+                    .sum()
+            },
+            |_| 0,
+        )
+    }
+
+    /// Whether the entry under `iter` can still be completed. Cheaper than `count` when
+    /// words are only excluded by weight and the entry has no letter sets.
+    pub fn any(&mut self, iter: &EntryIterator, cache: &mut FillCache, index: &Index) -> bool {
+        if self.filter.excludes_words() || !iter.blank_constraints().is_empty() {
+            return self.count(iter, cache, index) > 0;
+        }
+        match self.filter.min_weight {
+            None => cache.is_valid.is_valid(iter.clone(), index),
+            Some(min_weight) => *self.valid.get_or_insert_with(
+                iter.clone(),
+                |pattern| index.is_valid_with_min_weight(pattern, min_weight),
+                |_| 0,
+            ),
+        }
+    }
+
+    /// Narrows `order`, a list of positions in `words`, to the words the entry under `iter`
+    /// may take, desired ones first and otherwise in the order given.
+    pub fn apply(&self, iter: &EntryIterator, words: &[(Box<str>, i32)], order: &mut Vec<usize>) {
+        let constraints = iter.blank_constraints();
+        if !constraints.is_empty() {
+            order.retain(|i| satisfies(&words[*i].0, &constraints));
+        }
+        self.filter.apply(words, order);
+    }
+}
+
+/// The entry's pattern with its letter sets written out, e.g. `A[ST] E`.
+fn constrained_pattern(iter: &EntryIterator, constraints: &[(usize, &str)]) -> String {
+    let mut result = String::new();
+    let mut constraints = constraints.iter().peekable();
+    for (i, c) in iter.clone().enumerate() {
+        match constraints.peek() {
+            Some((j, letters)) if *j == i => {
+                result.push('[');
+                result.push_str(letters);
+                result.push(']');
+                constraints.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crossword::{Crossword, Direction, EntryLocation};
+
+    #[test]
+    fn any_remembers_patterns_at_min_weight() {
+        let index = Index::build(vec![(String::from("CAT"), 50), (String::from("COT"), 10)]);
+        let options = FillOptions {
+            min_weight: Some(20),
+            ..FillOptions::default()
+        };
+        let mut allowed = AllowedWords::new(WordFilter::new(&options).unwrap());
+        let mut cache = FillCache::default();
+        let location = EntryLocation::new(0, 0, 3, Direction::Across, false);
+
+        let crossword = Crossword::from_string(String::from("C T"), 3, 1).unwrap();
+        let iter = EntryIterator::new(&crossword, &location);
+        assert!(allowed.any(&iter, &mut cache, &index));
+        assert!(allowed.valid.contains("C T"));
+
+        let crossword = Crossword::from_string(String::from("CO "), 3, 1).unwrap();
+        let iter = EntryIterator::new(&crossword, &location);
+        assert!(!allowed.any(&iter, &mut cache, &index));
+        assert!(allowed.valid.contains("CO "));
+    }
+}
@@ -0,0 +1,132 @@
+//! Grid-wide letter requirements, such as pangrams or caps on a letter.
+//!
+//! Requirements are checked on every partial grid against the blank cells still to be
+//! filled, so branches that can no longer meet them are pruned as soon as possible.
+
+use serde::{Deserialize, Serialize};
+
+use super::FillOptions;
+use crate::Crossword;
+
+/// A number of cells holding any of `letters`, e.g. 2 of "JQXZ".
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LetterCount {
+    pub letters: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LetterRules {
+    pangram: bool,
+    /// Letter masks with the number of cells required from each.
+    minimums: Vec<(u32, usize)>,
+    maximums: Vec<(u32, usize)>,
+    /// Cells of the entries being filled.
+    fillable: Vec<usize>,
+}
+
+fn letter_mask(letters: &str) -> Result<u32, String> {
+    let mut mask = 0;
+    for c in letters.chars().filter(|c| !c.is_whitespace()) {
+        if !c.is_ascii_alphabetic() {
+            return Err(format!("Unexpected '{}' in letter count.", c));
+        }
+        mask |= 1 << (c.to_ascii_uppercase() as u8 - b'A');
+    }
+    Ok(mask)
+}
+
+impl LetterRules {
+    /// Builds the requirements in `options`, to be met by filling the blank cells in
+    /// `fillable`.
+    pub fn new(options: &FillOptions, fillable: Vec<usize>) -> Result<LetterRules, String> {
+        let masks = |counts: &[LetterCount]| {
+            counts
+                .iter()
+                .map(|c| Ok((letter_mask(&c.letters)?, c.count)))
+                .collect::<Result<Vec<_>, String>>()
+        };
+        Ok(LetterRules {
+            pangram: options.require_pangram,
+            minimums: masks(&options.min_letter_counts)?,
+            maximums: masks(&options.max_letter_counts)?,
+            fillable,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.pangram && self.minimums.is_empty() && self.maximums.is_empty()
+    }
+
+    /// Whether `candidate` can still meet every requirement once its fillable cells are
+    /// filled. Exact once no fillable cell is blank.
+    pub fn allows(&self, candidate: &Crossword) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let bytes = candidate.contents.as_bytes();
+        let mut counts = [0usize; 26];
+        for c in bytes.iter().filter(|c| c.is_ascii_uppercase()) {
+            counts[(c - b'A') as usize] += 1;
+        }
+        let blanks = self.fillable.iter().filter(|i| bytes[**i] == b' ').count();
+        let count_of = |mask: u32| -> usize {
+            (0..26)
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| counts[i])
+                .sum()
+        };
+
+        if self.pangram && counts.iter().filter(|count| **count == 0).count() > blanks {
+            return false;
+        }
+        self.maximums
+            .iter()
+            .all(|(mask, count)| count_of(*mask) <= *count)
+            && self
+                .minimums
+                .iter()
+                .all(|(mask, count)| count_of(*mask) + blanks >= *count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LetterCount, LetterRules};
+    use crate::{fill::FillOptions, Crossword};
+
+    #[test]
+    fn pruning() {
+        let grid = Crossword::from_string(String::from("JAZZ    "), 4, 2).unwrap();
+        let options = FillOptions {
+            min_letter_counts: vec![LetterCount {
+                letters: String::from("jqxz"),
+                count: 6,
+            }],
+            max_letter_counts: vec![LetterCount {
+                letters: String::from("Z"),
+                count: 2,
+            }],
+            ..FillOptions::default()
+        };
+
+        let rules = LetterRules::new(&options, (0..8).collect()).unwrap();
+        assert!(rules.allows(&grid));
+        // Only the first blank is fillable, leaving 3 + 1 < 6 high-value letters.
+        let rules = LetterRules::new(&options, vec![4]).unwrap();
+        assert!(!rules.allows(&grid));
+
+        let grid = Crossword::from_string(String::from("JAZZZ   "), 4, 2).unwrap();
+        let rules = LetterRules::new(&options, (0..8).collect()).unwrap();
+        assert!(!rules.allows(&grid));
+
+        let options = FillOptions {
+            require_pangram: true,
+            ..FillOptions::default()
+        };
+        let rules = LetterRules::new(&options, (0..8).collect()).unwrap();
+        assert!(!rules.allows(&grid));
+    }
+}
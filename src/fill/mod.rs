@@ -1,22 +1,112 @@
 use std::{
-    collections::{HashMap, HashSet},
-    hash::{self, BuildHasherDefault},
+    collections::HashMap,
+    hash::BuildHasherDefault,
+    sync::{atomic::AtomicBool, Arc},
 };
 
 use crossword::{Direction, EntryIterator, EntryLocation};
-use hash::{Hash, Hasher};
 use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
 
 use crate::{crossword, index::Index, Crossword};
 
 pub mod cache;
-use cache::{CachedIsValid, CachedWords};
+pub mod duplicates;
+pub mod filter;
+pub mod letters;
+pub mod order;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod restart;
+pub mod search;
+pub mod select;
+pub mod trail;
+use cache::FillCache;
+use duplicates::{DuplicateRules, UsedEntries};
+use filter::AllowedWords;
+use letters::LetterCount;
+use order::ValueOrdering;
+use restart::RestartPolicy;
+use search::FillStatus;
+use select::EntrySelection;
 use serde::{Deserialize, Serialize};
 
 pub struct Filler<'s> {
     index: &'s Index,
-    is_valid_cache: CachedIsValid,
-    word_cache: CachedWords,
+    options: FillOptions,
+    cache: FillCache,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+/// Options controlling how a `Filler` searches.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FillOptions {
+    /// Seed used to break ties between words of equal weight. Fills with the same seed,
+    /// grid and index are identical.
+    pub seed: Option<u64>,
+    /// Whether words of equal weight are tried in random order. When disabled they are
+    /// tried alphabetically and the seed is ignored.
+    pub randomize: bool,
+    /// Words never placed by this fill, on top of those missing from the index.
+    pub excluded_words: Vec<String>,
+    /// Regular expressions; words matching one of them in full (case-insensitively) are never
+    /// placed.
+    pub excluded_patterns: Vec<String>,
+    /// Words tried before any others wherever they fit.
+    pub desired_words: Vec<String>,
+    /// Rejects entries containing another entry, e.g. RUN and RERUN. Identical entries are
+    /// always rejected.
+    pub forbid_contained_entries: bool,
+    /// Rejects entries sharing their first this many letters, e.g. 4 for TRAIN and TRAINER.
+    /// Only the prefix is compared, so small lengths also reject unrelated words.
+    pub shared_prefix_length: Option<usize>,
+    /// Requires every letter of the alphabet somewhere in the grid.
+    pub require_pangram: bool,
+    /// Requires at least `count` cells holding one of `letters`, e.g. 2 of "JQXZ".
+    pub min_letter_counts: Vec<LetterCount>,
+    /// Allows at most `count` cells holding one of `letters`.
+    pub max_letter_counts: Vec<LetterCount>,
+    /// Words weighted below this are not used. `Filler::fill_relaxed` lowers it when no fill
+    /// exists at it.
+    pub min_weight: Option<i32>,
+    /// How far `fill_relaxed` lowers the minimum weight each time no fill exists at it.
+    /// Without a step the minimum is dropped altogether after the first failure.
+    pub min_weight_step: Option<i32>,
+    /// Tries words in a random order drawn with probability proportional to
+    /// `exp(weight / temperature)`, instead of by weight. Setting it without `randomize` makes
+    /// the fill invalid.
+    pub temperature: Option<f64>,
+    /// Starts the search over whenever a run spends its node budget, e.g.
+    /// `{Luby: {base: 1000}}`. Restarted runs break ties at random even when `randomize` is
+    /// off, seeded by `seed`. A policy whose budgets do not grow makes the fill invalid.
+    pub restarts: Option<RestartPolicy>,
+    /// How the next entry to fill is chosen, e.g. `"MrvDegree"`.
+    pub entry_selection: EntrySelection,
+    /// How the words for an entry are ordered, e.g. `"Lcv"`. Desired words still come first.
+    pub value_ordering: ValueOrdering,
+}
+
+impl Default for FillOptions {
+    fn default() -> FillOptions {
+        FillOptions {
+            seed: None,
+            randomize: true,
+            excluded_words: vec![],
+            excluded_patterns: vec![],
+            desired_words: vec![],
+            forbid_contained_entries: false,
+            shared_prefix_length: None,
+            require_pangram: false,
+            min_letter_counts: vec![],
+            max_letter_counts: vec![],
+            min_weight: None,
+            min_weight_step: None,
+            temperature: None,
+            restarts: None,
+            entry_selection: EntrySelection::Mrv,
+            value_ordering: ValueOrdering::WeightOnly,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -26,6 +116,66 @@ pub struct EntryLocationToFill {
     pub(crate) direction: Direction,
 }
 
+impl EntryLocationToFill {
+    /// The entries with a blank cell among `cells` (rows and columns), as filled by
+    /// `Filler::fill_region`. Fails if the region has no blank cell in an entry.
+    pub fn in_region(
+        crossword: &Crossword,
+        cells: &[(usize, usize)],
+    ) -> Result<Vec<EntryLocationToFill>, String> {
+        if cells
+            .iter()
+            .any(|(row, col)| *row >= crossword.height || *col >= crossword.width)
+        {
+            return Err(String::from("Region is outside the grid."));
+        }
+        let region: FxHashSet<(usize, usize)> = cells.iter().copied().collect();
+        let is_blank = |(row, col): (usize, usize)| {
+            crossword.contents.as_bytes()[row * crossword.width + col] == b' '
+        };
+
+        let result: Vec<EntryLocationToFill> = crossword
+            .get_entries()
+            .into_iter()
+            .filter(|entry_location| {
+                entry_location
+                    .cells()
+                    .any(|cell| is_blank(cell) && region.contains(&cell))
+            })
+            .map(|entry_location| EntryLocationToFill {
+                start_row: entry_location.start_row,
+                start_col: entry_location.start_col,
+                direction: entry_location.direction,
+            })
+            .collect();
+        match result.is_empty() {
+            true => Err(String::from("Region has no blank cells to fill.")),
+            false => Ok(result),
+        }
+    }
+}
+
+/// An entry and the word it holds, as listed by `RelaxedFill` and fill reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportEntry {
+    pub start_row: usize,
+    pub start_col: usize,
+    pub direction: Direction,
+    pub word: String,
+    /// `None` for entries not in the index.
+    pub weight: Option<i32>,
+}
+
+/// A fill found by `Filler::fill_relaxed`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RelaxedFill {
+    pub crossword: Crossword,
+    /// The minimum weight the fill was found at, `None` if it had to be dropped.
+    pub min_weight: Option<i32>,
+    /// Entries holding words weighted below the requested minimum, lowest first.
+    pub below_min_weight: Vec<ReportEntry>,
+}
+
 pub trait Fill {
     fn fill(
         &mut self,
@@ -36,43 +186,155 @@ pub trait Fill {
 
 impl<'s> Filler<'s> {
     pub fn new(index: &'s Index) -> Filler<'s> {
+        Filler::with_options(index, &FillOptions::default())
+    }
+
+    pub fn with_options(index: &'s Index, options: &FillOptions) -> Filler<'s> {
+        Filler::with_cache(index, options, FillCache::default())
+    }
+
+    /// Creates a filler reusing `cache`, which must have been filled from the same `index`.
+    pub fn with_cache(index: &'s Index, options: &FillOptions, cache: FillCache) -> Filler<'s> {
         Filler {
             index,
-            is_valid_cache: CachedIsValid::default(),
-            word_cache: CachedWords::default(),
+            options: options.clone(),
+            cache,
+            cancel: None,
+        }
+    }
+
+    /// Makes `fill` give up with an error once `cancel` is set.
+    pub fn set_cancel_flag(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Fills `crossword`, lowering `FillOptions::min_weight` by `min_weight_step` until a fill
+    /// exists, and reports the entries that needed lower-weighted words. `fill`, `start` and
+    /// `step` only ever search at the given minimum.
+    pub fn fill_relaxed(
+        &mut self,
+        crossword: &Crossword,
+        entry_locations_to_fill: Option<&Vec<EntryLocationToFill>>,
+    ) -> Result<RelaxedFill, String> {
+        let requested = self.options.min_weight;
+        let lowest = self.index.min_weight().unwrap_or(0);
+        let step = self.options.min_weight_step.filter(|step| *step > 0);
+
+        let mut min_weight = requested;
+        let result = loop {
+            self.options.min_weight = min_weight;
+            let mut search = self.start(crossword, entry_locations_to_fill);
+            match self.step(&mut search, usize::MAX) {
+                // At or below the index's lowest weight, the minimum already excludes nothing.
+                FillStatus::Failed if min_weight.is_some_and(|min| min > lowest) => {}
+                FillStatus::Filled(result) => break Ok(result.clone()),
+                FillStatus::Cancelled => break Err(String::from("Fill cancelled.")),
+                FillStatus::Invalid(e) => break Err(e.clone()),
+                FillStatus::Running | FillStatus::Failed => {
+                    break Err(String::from("Failed to fill."))
+                }
+            }
+            // Once no word is below the minimum, dropping it changes nothing.
+            min_weight = match (min_weight, step) {
+                (Some(min), Some(step)) if min.saturating_sub(step) > lowest => Some(min - step),
+                _ => None,
+            };
+        };
+        self.options.min_weight = requested;
+
+        let filled = result?;
+        let mut below_min_weight = vec![];
+        if let (Some(requested), true) = (requested, min_weight != requested) {
+            for entry_location in crossword.get_entries() {
+                if entry_location.prefilled {
+                    continue;
+                }
+                let word: String = EntryIterator::new(&filled, &entry_location).collect();
+                match self.index.get(&word) {
+                    Some(w) if w.weight < requested => below_min_weight.push(ReportEntry {
+                        start_row: entry_location.start_row,
+                        start_col: entry_location.start_col,
+                        direction: entry_location.direction.clone(),
+                        word,
+                        weight: Some(w.weight),
+                    }),
+                    _ => {}
+                }
+            }
+            below_min_weight.sort_by_key(|e| e.weight);
         }
+        Ok(RelaxedFill {
+            crossword: filled,
+            min_weight,
+            below_min_weight,
+        })
+    }
+
+    /// Fills the blank cells among `cells` (rows and columns), leaving the rest of the grid as
+    /// it is. Entries reaching past the region are searched in full, so their blank cells
+    /// outside it must still admit a word, but only the letters inside the region are kept.
+    pub fn fill_region(
+        &mut self,
+        crossword: &Crossword,
+        cells: &[(usize, usize)],
+    ) -> Result<Crossword, String> {
+        let entry_locations_to_fill = EntryLocationToFill::in_region(crossword, cells)?;
+        let mut filled = self.fill(crossword, Some(&entry_locations_to_fill))?;
+
+        let region: FxHashSet<usize> = cells
+            .iter()
+            .map(|(row, col)| row * crossword.width + col)
+            .collect();
+        for (i, c) in crossword.contents.bytes().enumerate() {
+            if c == b' ' && !region.contains(&i) {
+                filled.set_cell(i, b' ');
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Consumes the filler, returning its cache so it can be reused by the next fill.
+    pub fn into_cache(self) -> FillCache {
+        self.cache
     }
 }
 
+/// Checks the entries in `entry_locations` after a word was placed: incomplete ones must
+/// still have an allowed completion and complete ones must be allowed and not clash with any other
+/// entry. Entries complete in `used` are skipped, as they cannot have changed.
 pub fn is_valid_grid(
     candidate: &Crossword,
     entry_locations: &[&EntryLocation],
     index: &Index,
-    mut used_words: FxHashSet<u64>,
-    is_valid_cache: &mut CachedIsValid,
-) -> (bool, FxHashSet<u64>) {
-    for entry_location in entry_locations {
-        let iter = EntryIterator::new(candidate, entry_location);
+    used: &mut UsedEntries,
+    rules: &DuplicateRules,
+    cache: &mut FillCache,
+    allowed: &mut AllowedWords,
+) -> bool {
+    used.start_check();
 
-        let mut hasher = FxHasher::default();
-        let mut full = true;
-        for c in iter.clone() {
-            c.hash(&mut hasher);
-            full &= c != ' ';
+    for entry_location in entry_locations {
+        if used.contains_location(entry_location) {
+            continue;
         }
-        let key = hasher.finish();
+        let iter = EntryIterator::new(candidate, entry_location);
 
-        if full && used_words.contains(&key) {
-            return (false, used_words);
+        if iter.clone().all(|c| c != ' ') {
+            let word = match used.check(iter.clone(), rules) {
+                Some(word) => word,
+                None => return false,
+            };
+            // Crossings completed as a side effect must not slip an excluded word in.
+            if !entry_location.prefilled && !allowed.filter.allows_entry(word, index) {
+                return false;
+            }
         }
 
-        used_words.insert(key);
-
-        if !entry_location.prefilled && !is_valid_cache.is_valid(iter, index) {
-            return (false, used_words);
+        if !entry_location.prefilled && !allowed.any(&iter, cache, index) {
+            return false;
         }
     }
-    (true, used_words)
+    true
 }
 
 pub fn get_orthogonal_words<'s>(
@@ -158,7 +420,10 @@ pub fn fill_one_word(candidate: &Crossword, chars: &EntryIterator, word: &str) -
 
     Crossword {
         contents: result_contents,
-        ..*candidate
+        width: candidate.width,
+        height: candidate.height,
+        constraints: candidate.constraints.clone(),
+        locks: candidate.locks.clone(),
     }
 }
 
@@ -168,95 +433,20 @@ impl<'s> Fill for Filler<'s> {
         crossword: &Crossword,
         entry_locations_to_fill: Option<&Vec<EntryLocationToFill>>,
     ) -> Result<Crossword, String> {
-        let mut num_candidates = 0;
-        let mut candidates = vec![crossword.to_owned()];
-
-        let entry_locations = crossword.get_entries();
-        let entry_location_lookup = build_square_to_entry_lookup(&entry_locations);
-
-        let mut used_words = HashSet::with_capacity_and_hasher(
-            entry_locations.len(),
-            BuildHasherDefault::<FxHasher>::default(),
-        );
+        let mut search = self.start(crossword, entry_locations_to_fill);
 
-        let mut entry_locations_to_fill_lookup = FxHashSet::default();
-        if entry_locations_to_fill.is_some() {
-            for entry_location in entry_locations_to_fill.unwrap() {
-                entry_locations_to_fill_lookup.insert((
-                    entry_location.direction.clone(),
-                    entry_location.start_row,
-                    entry_location.start_col,
-                ));
-            }
+        match self.step(&mut search, usize::MAX) {
+            FillStatus::Filled(result) => Ok(result.clone()),
+            FillStatus::Cancelled => Err(String::from("Fill cancelled.")),
+            FillStatus::Invalid(e) => Err(e.clone()),
+            FillStatus::Running | FillStatus::Failed => Err(String::from("Failed to fill.")),
         }
-        let partial_fill = entry_locations_to_fill.is_some();
-
-        while let Some(candidate) = candidates.pop() {
-            num_candidates += 1;
-            // Find the next entry to fill, sorted by # possible words and start position.
-            let to_fill_options = entry_locations
-                .iter()
-                .filter(|iter| {
-                    !partial_fill
-                        || entry_locations_to_fill_lookup.contains(&(
-                            (*iter).direction.clone(),
-                            (*iter).start_row,
-                            (*iter).start_col,
-                        ))
-                })
-                .map(|entry_location| EntryIterator::new(&candidate, entry_location))
-                .filter(|iter| iter.clone().any(|c| c == ' '));
-
-            let entries_left = to_fill_options.clone().count();
-
-            let to_fill_option = to_fill_options.min_by_key(|iter| {
-                (
-                    self.word_cache.words(iter.clone(), self.index).len(),
-                    iter.entry_location.start_row,
-                    iter.entry_location.start_col,
-                )
-            });
-
-            if !to_fill_option.is_some() {
-                return Ok(candidate);
-            }
-
-            let to_fill = to_fill_option.unwrap();
-
-            let potential_fills = self.word_cache.words(to_fill.clone(), self.index);
-
-            let orthogonal_words =
-                get_orthogonal_words(&to_fill.entry_location, &entry_location_lookup);
-
-            for potential_fill in potential_fills {
-                let new_candidate = fill_one_word(&candidate, &to_fill.clone(), &potential_fill);
-
-                let (valid, tmp) = is_valid_grid(
-                    &new_candidate,
-                    &orthogonal_words,
-                    self.index,
-                    used_words,
-                    &mut self.is_valid_cache,
-                );
-                used_words = tmp;
-                used_words.clear();
-
-                if valid {
-                    if entries_left == 1 || !new_candidate.contents.contains(' ') {
-                        return Ok(new_candidate);
-                    }
-                    candidates.push(new_candidate);
-                }
-            }
-        }
-
-        Err(String::from("Failed to fill."))
     }
 }
 
-pub fn build_square_to_entry_lookup<'s>(
-    entry_locations: &'s [EntryLocation],
-) -> FxHashMap<(Direction, usize, usize), &'s EntryLocation> {
+pub fn build_square_to_entry_lookup(
+    entry_locations: &[EntryLocation],
+) -> FxHashMap<(Direction, usize, usize), &EntryLocation> {
     let mut result = FxHashMap::default();
 
     for entry_location in entry_locations {
@@ -294,13 +484,19 @@ pub fn build_square_to_entry_lookup<'s>(
 #[cfg(test)]
 mod tests {
 
-    use crate::{crossword::Direction, fill::Fill, index::Index};
+    use crate::{
+        crossword::{Direction, EntryIterator},
+        fill::Fill,
+        index::Index,
+    };
 
     use crate::Crossword;
 
-    use std::{cmp::Ordering, time::Instant};
+    use std::time::Instant;
 
-    use super::{EntryLocationToFill, Filler};
+    use super::{
+        cache::FillCache, letters::LetterCount, search, EntryLocationToFill, FillOptions, Filler,
+    };
 
     #[test]
     fn medium_grid() {
@@ -452,4 +648,358 @@ STRAWBERRY*
         println!("Filled in {} seconds.", now.elapsed().as_secs());
         println!("{}", filled_puz);
     }
+
+    #[test]
+    fn seeded_fills_are_reproducible() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+
+        let options = FillOptions {
+            seed: Some(7),
+            ..FillOptions::default()
+        };
+        let first = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        let second = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        assert_eq!(first, second);
+
+        let options = FillOptions {
+            randomize: false,
+            ..FillOptions::default()
+        };
+        let first = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        let second = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn cache_stays_warm_across_fills() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let options = FillOptions {
+            seed: Some(1),
+            ..FillOptions::default()
+        };
+
+        let mut filler = Filler::with_cache(&index, &options, FillCache::default());
+        let first = filler.fill(&grid, None).unwrap();
+        let cache = filler.into_cache();
+        let warm_entries = cache.words.cache().len();
+        assert!(warm_entries > 0);
+
+        // The same search again is answered entirely from the warm cache.
+        let mut filler = Filler::with_cache(&index, &options, cache);
+        assert_eq!(filler.fill(&grid, None).unwrap(), first);
+        assert_eq!(filler.into_cache().words.cache().len(), warm_entries);
+    }
+
+    #[test]
+    fn excluded_and_desired_words() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let rows = |crossword: &Crossword| -> Vec<String> {
+            (0..4)
+                .map(|row| crossword.contents[row * 5..row * 5 + 5].to_string())
+                .collect()
+        };
+
+        let options = FillOptions {
+            randomize: false,
+            ..FillOptions::default()
+        };
+        let first = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+
+        let options = FillOptions {
+            randomize: false,
+            excluded_words: vec![rows(&first)[0].to_lowercase()],
+            excluded_patterns: vec![String::from(".*E.*")],
+            ..FillOptions::default()
+        };
+        let second = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        assert!(!rows(&second).contains(&rows(&first)[0]));
+        assert!(!second.contents.contains('E'));
+
+        let options = FillOptions {
+            randomize: false,
+            desired_words: vec![rows(&second)[2].clone()],
+            ..FillOptions::default()
+        };
+        let third = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        assert!(rows(&third).contains(&rows(&second)[2]));
+
+        let options = FillOptions {
+            excluded_patterns: vec![String::from("(")],
+            ..FillOptions::default()
+        };
+        assert!(Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .is_err());
+    }
+
+    #[test]
+    fn no_duplicate_entries_anywhere() {
+        let grid = Crossword::from_string(String::from("         "), 3, 3).unwrap();
+        let words =
+            |words: &[&str]| Index::build(words.iter().map(|w| (String::from(*w), 0)).collect());
+
+        // The only fill is a symmetric word square, which repeats every entry.
+        let index = words(&["BIT", "ICE", "TEN"]);
+        assert!(Filler::new(&index).fill(&grid, None).is_err());
+
+        let index = words(&[
+            "BIT", "ICE", "TEN", "CAT", "ORE", "WED", "COW", "ARE", "TED",
+        ]);
+        let filled = Filler::new(&index).fill(&grid, None).unwrap();
+        let mut entries: Vec<String> = filled
+            .get_entries()
+            .iter()
+            .map(|e| EntryIterator::new(&filled, e).collect())
+            .collect();
+        entries.sort();
+        entries.dedup();
+        assert_eq!(entries.len(), 6);
+    }
+
+    #[test]
+    fn letter_sets_are_honored() {
+        let grid = Crossword::from_string(
+            String::from("[AEIOU]    [^AEIOU]    [XYZ]        [ST]"),
+            5,
+            4,
+        )
+        .unwrap();
+        let index = Index::build_default();
+
+        let filled = Filler::new(&index).fill(&grid, None).unwrap();
+        let cell = |i: usize| filled.contents.as_bytes()[i] as char;
+        assert!("AEIOU".contains(cell(0)));
+        assert!(!"AEIOU".contains(cell(5)));
+        assert!("XYZ".contains(cell(10)));
+        assert!("ST".contains(cell(19)));
+
+        // Cells a region fill leaves blank keep their sets.
+        let row: Vec<(usize, usize)> = (0..5).map(|col| (0, col)).collect();
+        let filled = Filler::new(&index).fill_region(&grid, &row).unwrap();
+        assert!(filled.to_grid_string().ends_with("[ST]"));
+    }
+
+    #[test]
+    fn region_fill_keeps_the_rest() {
+        let grid = Crossword::from_string(
+            String::from(
+                "
+SLABS
+     
+     
+     
+",
+            ),
+            5,
+            4,
+        )
+        .unwrap();
+        let index = Index::build_default();
+
+        // The entries through the corner reach past it, so only its own cells are filled.
+        let corner = [(1, 0), (1, 1), (2, 0), (2, 1)];
+        assert_eq!(
+            EntryLocationToFill::in_region(&grid, &corner)
+                .unwrap()
+                .len(),
+            4
+        );
+        let filled = Filler::new(&index).fill_region(&grid, &corner).unwrap();
+        let cell = |row: usize, col: usize| filled.contents.as_bytes()[row * 5 + col];
+        assert!(filled.contents.starts_with("SLABS"));
+        for row in 1..4 {
+            for col in 0..5 {
+                assert_eq!(cell(row, col) == b' ', !corner.contains(&(row, col)));
+            }
+        }
+        // The entries left partly blank can still be completed.
+        for entry_location in filled.get_entries() {
+            assert!(index.is_valid(EntryIterator::new(&filled, &entry_location)));
+        }
+
+        // The bottom three rows: all entries lie within it.
+        let rows: Vec<(usize, usize)> = (1..4)
+            .flat_map(|row| (0..5).map(move |col| (row, col)))
+            .collect();
+        let entries = EntryLocationToFill::in_region(&grid, &rows).unwrap();
+        assert_eq!(entries.len(), 8);
+
+        // Row 1 only: the downs are searched too, but keep only their first letter.
+        let row: Vec<(usize, usize)> = (0..5).map(|col| (1, col)).collect();
+        let filled = Filler::new(&index).fill_region(&grid, &row).unwrap();
+        assert!(filled.contents.starts_with("SLABS"));
+        assert!(!filled.contents[5..10].contains(' '));
+        assert_eq!(&filled.contents[10..], "          ");
+
+        // Regions without blank cells are rejected rather than filling nothing.
+        assert!(Filler::new(&index).fill_region(&grid, &[]).is_err());
+        assert!(Filler::new(&index).fill_region(&grid, &[(0, 0)]).is_err());
+        assert!(Filler::new(&index).fill_region(&grid, &[(9, 9)]).is_err());
+    }
+
+    #[test]
+    fn locks_survive_refills() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let options = FillOptions {
+            seed: Some(5),
+            ..FillOptions::default()
+        };
+        let mut filler = Filler::with_options(&index, &options);
+
+        let mut filled = filler.fill(&grid, None).unwrap();
+        let entries = filled.get_entries();
+        filled.lock_entry(&entries[0]);
+        let kept = filled.contents[..5].to_string();
+
+        for _ in 0..2 {
+            filled.clear_unlocked();
+            assert_eq!(filled.contents.trim_end(), kept);
+            filled = filler.fill(&filled, None).unwrap();
+            assert!(filled.is_entry_locked(&entries[0]));
+            assert!(filled.contents.starts_with(&kept));
+        }
+    }
+
+    #[test]
+    fn letter_counts_are_enforced() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let options = FillOptions {
+            seed: Some(2),
+            min_letter_counts: vec![LetterCount {
+                letters: String::from("JQXZ"),
+                count: 2,
+            }],
+            max_letter_counts: vec![LetterCount {
+                letters: String::from("E"),
+                count: 1,
+            }],
+            ..FillOptions::default()
+        };
+
+        let filled = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        let count = |letters: &str| {
+            filled
+                .contents
+                .chars()
+                .filter(|c| letters.contains(*c))
+                .count()
+        };
+        assert!(count("JQXZ") >= 2);
+        assert!(count("E") <= 1);
+
+        // 20 cells cannot hold 26 letters, so this fails without searching.
+        let options = FillOptions {
+            require_pangram: true,
+            ..FillOptions::default()
+        };
+        let mut filler = Filler::with_options(&index, &options);
+        let mut search = filler.start(&grid, None);
+        filler.step(&mut search, usize::MAX);
+        assert_eq!(search.status(), &search::FillStatus::Failed);
+        assert_eq!(search.nodes(), 1);
+
+        // A grid with nothing left to fill is still held to the rules.
+        let complete = Crossword::from_string(String::from("CATORETEE"), 3, 3).unwrap();
+        assert!(filler.fill(&complete, None).is_err());
+    }
+
+    #[test]
+    fn min_weight_is_relaxed() {
+        let grid = Crossword::from_string(String::from("         "), 3, 3).unwrap();
+        let mut words: Vec<(String, i32)> = ["BIT", "ICE", "TEN"]
+            .iter()
+            .map(|w| (String::from(*w), 50))
+            .collect();
+        words.extend(
+            ["CAT", "ORE", "WED", "COW", "ARE"]
+                .iter()
+                .map(|w| (String::from(*w), 20)),
+        );
+        words.push((String::from("TED"), 5));
+        words.push((String::from("ZZZ"), 0));
+        let index = Index::build(words);
+
+        let options = FillOptions {
+            min_weight: Some(50),
+            min_weight_step: Some(9),
+            ..FillOptions::default()
+        };
+        let mut filler = Filler::with_options(&index, &options);
+        let result = filler.fill_relaxed(&grid, None).unwrap();
+        assert_eq!(result.min_weight, Some(5));
+        assert_eq!(result.below_min_weight.len(), 6);
+        assert_eq!(result.below_min_weight[0].word, "TED");
+
+        // Without a step the minimum is dropped at once.
+        let options = FillOptions {
+            min_weight: Some(50),
+            ..FillOptions::default()
+        };
+        let mut filler = Filler::with_options(&index, &options);
+        assert_eq!(filler.fill_relaxed(&grid, None).unwrap().min_weight, None);
+        // `fill` keeps to the minimum, like `start` and `step`.
+        assert!(filler.fill(&grid, None).is_err());
+
+        // A minimum at the index's lowest weight excludes nothing, so there is no retry.
+        let options = FillOptions {
+            min_weight: Some(0),
+            ..FillOptions::default()
+        };
+        let mut filler = Filler::with_options(&index, &options);
+        let grid = Crossword::from_string(String::from("Q        "), 3, 3).unwrap();
+        assert_eq!(
+            filler.fill_relaxed(&grid, None).unwrap_err(),
+            "Failed to fill."
+        );
+    }
+
+    #[test]
+    fn sampled_fills_vary() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let fill = |seed: u64, temperature: f64| {
+            let options = FillOptions {
+                seed: Some(seed),
+                temperature: Some(temperature),
+                ..FillOptions::default()
+            };
+            Filler::with_options(&index, &options).fill(&grid, None)
+        };
+
+        assert_eq!(fill(4, 1.0).unwrap(), fill(4, 1.0).unwrap());
+        assert_ne!(fill(4, 1.0).unwrap(), fill(5, 1.0).unwrap());
+        assert!(fill(4, 0.0).is_err());
+
+        let options = FillOptions {
+            randomize: false,
+            temperature: Some(1.0),
+            ..FillOptions::default()
+        };
+        let error = Filler::with_options(&index, &options).fill(&grid, None);
+        assert_eq!(
+            error,
+            Err(String::from("Temperature needs randomize to be set."))
+        );
+    }
 }
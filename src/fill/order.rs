@@ -0,0 +1,254 @@
+//! Choosing the order a search tries words in.
+
+use serde::{Deserialize, Serialize};
+
+use super::{select::SelectionContext, trail::TrailGrid};
+use crate::crossword::{Direction, EntryIterator, EntryLocation};
+
+/// Reorders the candidate words of an entry before the search tries them.
+pub trait ValueOrderer {
+    /// `order` lists the positions in `words` of the words fitting `entry` in `grid`, ordered
+    /// by weight with ties broken as `FillOptions` asks. Desired words are moved back to the
+    /// front afterwards. Words may be tried out on `grid`, as long as it is left as it was.
+    fn order(
+        &self,
+        grid: &mut TrailGrid,
+        entry: &EntryLocation,
+        words: &[(Box<str>, i32)],
+        order: &mut Vec<usize>,
+        context: &mut SelectionContext,
+    );
+}
+
+/// The built-in `ValueOrderer`s, chosen by `FillOptions::value_ordering`.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ValueOrdering {
+    /// Highest weight first.
+    #[default]
+    WeightOnly,
+    /// Least constraining value: words leaving the crossings the most options first. Counting
+    /// the options is costly for entries with many candidates.
+    Lcv,
+    /// Ranks words by weight and by options left, trying the lowest mix of the two ranks
+    /// first. `lcv_share` runs from 0 (weight only) to 1 (options only), e.g.
+    /// `{Blend: {lcv_share: 0.5}}`.
+    Blend { lcv_share: f64 },
+}
+
+impl ValueOrdering {
+    pub fn orderer(self) -> Box<dyn ValueOrderer> {
+        match self {
+            ValueOrdering::WeightOnly => Box::new(WeightOnly),
+            ValueOrdering::Lcv => Box::new(Lcv),
+            ValueOrdering::Blend { lcv_share } => Box::new(Blend {
+                lcv_share: lcv_share.clamp(0.0, 1.0),
+            }),
+        }
+    }
+}
+
+/// Total number of words the crossings through blank cells of `entry` may take once `word`
+/// is placed, or 0 if one of them has none left.
+pub fn remaining_options(
+    grid: &mut TrailGrid,
+    entry: &EntryLocation,
+    word: &str,
+    context: &mut SelectionContext,
+) -> u64 {
+    let direction = match entry.direction {
+        Direction::Across => Direction::Down,
+        Direction::Down => Direction::Across,
+    };
+    let crossings: Vec<&EntryLocation> = entry
+        .cells()
+        .filter(|(row, col)| grid.grid().contents.as_bytes()[row * grid.grid().width + col] == b' ')
+        .filter_map(|(row, col)| {
+            context
+                .crossings
+                .get(&(direction.clone(), row, col))
+                .copied()
+        })
+        .filter(|crossing| crossing.length > 1)
+        .collect();
+
+    let mark = grid.mark();
+    grid.place(entry, word);
+    let mut total = 0;
+    for crossing in crossings {
+        match context.count(&EntryIterator::new(grid.grid(), crossing)) {
+            0 => {
+                total = 0;
+                break;
+            }
+            count => total += count as u64,
+        }
+    }
+    grid.undo(mark);
+    total
+}
+
+/// Positions in `order` from most to fewest options left, keeping their order otherwise.
+fn lcv_order(
+    grid: &mut TrailGrid,
+    entry: &EntryLocation,
+    words: &[(Box<str>, i32)],
+    order: &[usize],
+    context: &mut SelectionContext,
+) -> Vec<usize> {
+    let options: Vec<u64> = order
+        .iter()
+        .map(|i| remaining_options(grid, entry, &words[*i].0, context))
+        .collect();
+    let mut by_options: Vec<usize> = (0..order.len()).collect();
+    by_options.sort_by_key(|i| std::cmp::Reverse(options[*i]));
+    by_options
+}
+
+/// Moves the entry at `order[positions[i]]` to position `i`.
+fn rearrange(order: &mut Vec<usize>, positions: Vec<usize>) {
+    *order = positions.into_iter().map(|i| order[i]).collect();
+}
+
+pub struct WeightOnly;
+
+impl ValueOrderer for WeightOnly {
+    fn order(
+        &self,
+        _grid: &mut TrailGrid,
+        _entry: &EntryLocation,
+        _words: &[(Box<str>, i32)],
+        _order: &mut Vec<usize>,
+        _context: &mut SelectionContext,
+    ) {
+    }
+}
+
+pub struct Lcv;
+
+impl ValueOrderer for Lcv {
+    fn order(
+        &self,
+        grid: &mut TrailGrid,
+        entry: &EntryLocation,
+        words: &[(Box<str>, i32)],
+        order: &mut Vec<usize>,
+        context: &mut SelectionContext,
+    ) {
+        let positions = lcv_order(grid, entry, words, order, context);
+        rearrange(order, positions);
+    }
+}
+
+pub struct Blend {
+    pub lcv_share: f64,
+}
+
+impl ValueOrderer for Blend {
+    fn order(
+        &self,
+        grid: &mut TrailGrid,
+        entry: &EntryLocation,
+        words: &[(Box<str>, i32)],
+        order: &mut Vec<usize>,
+        context: &mut SelectionContext,
+    ) {
+        let mut lcv_ranks = vec![0; order.len()];
+        for (rank, i) in lcv_order(grid, entry, words, order, context)
+            .into_iter()
+            .enumerate()
+        {
+            lcv_ranks[i] = rank;
+        }
+        let mix =
+            |i: usize| (1.0 - self.lcv_share) * i as f64 + self.lcv_share * lcv_ranks[i] as f64;
+
+        let mut positions: Vec<usize> = (0..order.len()).collect();
+        positions.sort_by(|a, b| mix(*a).total_cmp(&mix(*b)));
+        rearrange(order, positions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use super::ValueOrdering;
+    use crate::{
+        crossword::{Direction, EntryIterator},
+        fill::{
+            build_square_to_entry_lookup, cache::FillCache, filter::AllowedWords,
+            select::SelectionContext, trail::TrailGrid, Fill, FillOptions, Filler,
+        },
+        index::Index,
+        Crossword,
+    };
+
+    #[test]
+    fn least_constraining_first() {
+        let index = Index::build(vec![
+            (String::from("AB"), 10),
+            (String::from("AC"), 10),
+            (String::from("BD"), 10),
+            (String::from("CD"), 10),
+            (String::from("XY"), 50),
+        ]);
+        let grid = Crossword::from_string(String::from("    "), 2, 2).unwrap();
+        let entries = grid.get_entries();
+        let top = entries
+            .iter()
+            .find(|e| e.direction == Direction::Across && e.start_row == 0)
+            .unwrap();
+        let crossings = build_square_to_entry_lookup(&entries);
+        let mut cache = FillCache::default();
+        let mut allowed = AllowedWords::default();
+
+        let words = cache
+            .words
+            .words(EntryIterator::new(&grid, top), &index)
+            .clone();
+        let mut order = |ordering: ValueOrdering| {
+            let mut order: Vec<usize> = (0..words.len()).collect();
+            ordering.orderer().order(
+                &mut TrailGrid::new(grid.clone()),
+                top,
+                &words,
+                &mut order,
+                &mut SelectionContext {
+                    allowed: &mut allowed,
+                    cache: &mut cache,
+                    index: &index,
+                    crossings: &crossings,
+                    theme_cells: &FxHashSet::default(),
+                    width: grid.width,
+                },
+            );
+            order
+                .into_iter()
+                .map(|i| words[i].0.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(ValueOrdering::WeightOnly)[0], "XY");
+        // Only AB and AC leave a word for both down entries.
+        assert_eq!(
+            order(ValueOrdering::Lcv),
+            vec!["AB", "AC", "XY", "BD", "CD"]
+        );
+        assert_eq!(
+            order(ValueOrdering::Blend { lcv_share: 0.0 }),
+            order(ValueOrdering::WeightOnly)
+        );
+
+        let index = Index::build_default();
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let options = FillOptions {
+            seed: Some(2),
+            value_ordering: ValueOrdering::Blend { lcv_share: 0.5 },
+            ..FillOptions::default()
+        };
+        let filled = Filler::with_options(&index, &options)
+            .fill(&grid, None)
+            .unwrap();
+        assert!(!filled.contents.contains(' '));
+    }
+}
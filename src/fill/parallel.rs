@@ -0,0 +1,192 @@
+//! Portfolio search across threads for native builds.
+//!
+//! Every thread runs its own `Filler` over the shared `Index` with a different tie-breaking
+//! seed. The first thread to find a fill wins and the others are cancelled. Every thread
+//! searches the whole space, so the first one to fail has also proved there is no fill and
+//! cancels the others too.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use rand::random;
+
+use super::{EntryLocationToFill, Fill, FillOptions, FillStatus, Filler};
+use crate::{index::Index, Crossword};
+
+pub struct ParallelFiller<'s> {
+    index: &'s Index,
+    options: FillOptions,
+    threads: usize,
+}
+
+impl<'s> ParallelFiller<'s> {
+    /// Creates a filler using one thread per available core.
+    pub fn new(index: &'s Index, options: &FillOptions) -> ParallelFiller<'s> {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        ParallelFiller::with_threads(index, options, threads)
+    }
+
+    pub fn with_threads(
+        index: &'s Index,
+        options: &FillOptions,
+        threads: usize,
+    ) -> ParallelFiller<'s> {
+        ParallelFiller {
+            index,
+            options: options.clone(),
+            threads: threads.max(1),
+        }
+    }
+
+    /// Options for the `i`th thread. The first thread uses the given options unchanged; the
+    /// rest always randomize, with seeds derived from the base seed.
+    fn thread_options(&self, i: usize, base_seed: u64) -> FillOptions {
+        if i == 0 {
+            return self.options.clone();
+        }
+        let mut options = self.options.clone();
+        options.seed = Some(base_seed.wrapping_add(i as u64));
+        options.randomize = true;
+        options
+    }
+}
+
+impl<'s> Fill for ParallelFiller<'s> {
+    fn fill(
+        &mut self,
+        crossword: &Crossword,
+        entry_locations_to_fill: Option<&Vec<EntryLocationToFill>>,
+    ) -> Result<Crossword, String> {
+        // Threads after the first always randomize, so check the options as given up front.
+        let search = Filler::with_options(self.index, &self.options)
+            .start(crossword, entry_locations_to_fill);
+        if let FillStatus::Invalid(e) = search.status() {
+            return Err(e.clone());
+        }
+
+        let base_seed = self.options.seed.unwrap_or_else(random);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for i in 0..self.threads {
+                let options = self.thread_options(i, base_seed);
+                let cancel = cancel.clone();
+                let sender = sender.clone();
+                let index = self.index;
+
+                scope.spawn(move || {
+                    let status =
+                        run_thread(index, &options, crossword, entry_locations_to_fill, &cancel);
+                    // The receiver outlives the scope, so this cannot fail.
+                    sender.send(status).unwrap();
+                });
+            }
+        });
+        drop(sender);
+
+        // Threads cancelled by another's result do not decide the outcome.
+        let mut error = None;
+        for status in receiver {
+            match status {
+                FillStatus::Filled(filled) => return Ok(filled),
+                FillStatus::Failed => error = Some(String::from("Failed to fill.")),
+                FillStatus::Invalid(e) => error = Some(e),
+                FillStatus::Running | FillStatus::Cancelled => {}
+            }
+        }
+        Err(error.unwrap_or_else(|| String::from("Fill cancelled.")))
+    }
+}
+
+/// Runs one thread's search, setting `cancel` once it has a definite result so the other
+/// threads stop.
+fn run_thread(
+    index: &Index,
+    options: &FillOptions,
+    crossword: &Crossword,
+    entry_locations_to_fill: Option<&Vec<EntryLocationToFill>>,
+    cancel: &Arc<AtomicBool>,
+) -> FillStatus {
+    let mut filler = Filler::with_options(index, options);
+    filler.set_cancel_flag(cancel.clone());
+    let mut search = filler.start(crossword, entry_locations_to_fill);
+    let status = filler.step(&mut search, usize::MAX).clone();
+    if status != FillStatus::Cancelled {
+        cancel.store(true, Ordering::Relaxed);
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::{run_thread, ParallelFiller};
+    use crate::{
+        fill::{Fill, FillOptions, FillStatus, Filler},
+        index::Index,
+        Crossword,
+    };
+
+    #[test]
+    fn portfolio_fill() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+
+        let mut filler = ParallelFiller::with_threads(&index, &FillOptions::default(), 4);
+        let filled = filler.fill(&grid, None).unwrap();
+
+        assert!(!filled.contents.contains(' '));
+    }
+
+    #[test]
+    fn invalid_options_fail_like_filler() {
+        let grid = Crossword::from_string(String::from("    "), 2, 2).unwrap();
+        let index = Index::build_default();
+        let options = FillOptions {
+            randomize: false,
+            temperature: Some(1.0),
+            ..FillOptions::default()
+        };
+
+        let expected = Filler::with_options(&index, &options).fill(&grid, None);
+        let mut filler = ParallelFiller::with_threads(&index, &options, 4);
+        assert_eq!(filler.fill(&grid, None), expected);
+        assert_eq!(
+            expected.unwrap_err(),
+            "Temperature needs randomize to be set."
+        );
+    }
+
+    #[test]
+    fn first_failure_cancels_the_rest() {
+        let index = Index::build(vec![
+            (String::from("BIT"), 0),
+            (String::from("ICE"), 0),
+            (String::from("TEN"), 0),
+        ]);
+        let grid = Crossword::from_string(String::from("         "), 3, 3).unwrap();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let status = run_thread(&index, &FillOptions::default(), &grid, None, &cancel);
+        assert_eq!(status, FillStatus::Failed);
+        assert!(cancel.load(Ordering::Relaxed));
+
+        // The cancelled threads do not turn the failure into a cancellation.
+        let mut filler = ParallelFiller::with_threads(&index, &FillOptions::default(), 8);
+        assert_eq!(
+            filler.fill(&grid, None),
+            Err(String::from("Failed to fill."))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Restarting a search that is stuck in one huge subtree.
+//!
+//! After its node budget runs out a search starts over from the initial grid, trying words of
+//! equal weight in a new random order. Partial grids whose whole subtree was explored without
+//! a fill are remembered as nogoods and skipped in later runs: the subtree under a grid does
+//! not depend on the order words are tried in, so they cannot lead to a fill either. Nogoods
+//! are kept by their exact contents, in a bounded cache that forgets the oldest first.
+
+use serde::{Deserialize, Serialize};
+
+use super::cache::{BoundedCache, DEFAULT_CACHE_BYTES};
+
+/// Memory budget for the nogoods of a search, in bytes.
+const NOGOOD_BYTES: usize = DEFAULT_CACHE_BYTES / 8;
+
+/// How many nodes each run of a search may expand before it restarts.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum RestartPolicy {
+    /// Runs of `base` times the Luby sequence 1, 1, 2, 1, 1, 2, 4, ... nodes.
+    Luby { base: usize },
+    /// Runs of `initial`, `initial * factor`, `initial * factor^2`, ... nodes.
+    Geometric { initial: usize, factor: f64 },
+}
+
+impl RestartPolicy {
+    /// Checks that budgets start at one node or more and keep growing. A run only proves a
+    /// grid has no fill by exhausting it, so a search whose budgets stay small never ends.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            RestartPolicy::Luby { base } if *base == 0 => {
+                Err(String::from("Restart base must be at least 1."))
+            }
+            RestartPolicy::Geometric { initial, .. } if *initial == 0 => {
+                Err(String::from("Restart initial budget must be at least 1."))
+            }
+            RestartPolicy::Geometric { factor, .. } if !factor.is_finite() || *factor <= 1.0 => {
+                Err(String::from("Restart factor must be greater than 1."))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Node budget of the run after `restarts` restarts, for a policy passing `validate`.
+    pub fn budget(&self, restarts: usize) -> usize {
+        match self {
+            RestartPolicy::Luby { base } => base.saturating_mul(luby(restarts + 1)),
+            RestartPolicy::Geometric { initial, factor } => {
+                let budget = *initial as f64 * factor.powi(restarts as i32);
+                budget.min(usize::MAX as f64) as usize
+            }
+        }
+    }
+}
+
+/// The `i`th term (from 1) of the Luby sequence.
+fn luby(i: usize) -> usize {
+    let mut i = i;
+    loop {
+        // The smallest k with 2^k - 1 >= i.
+        let k = usize::BITS - i.leading_zeros();
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+/// Restart bookkeeping of a `FillSearch`.
+pub struct Restarts {
+    policy: RestartPolicy,
+    count: usize,
+    nodes_left: usize,
+    nogoods: BoundedCache<()>,
+}
+
+impl Restarts {
+    pub fn new(policy: RestartPolicy) -> Restarts {
+        Restarts {
+            nodes_left: policy.budget(0),
+            policy,
+            count: 0,
+            nogoods: BoundedCache::with_capacity(NOGOOD_BYTES),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Counts one expanded node, returning whether the run's budget is spent and the search
+    /// should start over.
+    pub fn expanded(&mut self) -> bool {
+        self.nodes_left = self.nodes_left.saturating_sub(1);
+        if self.nodes_left > 0 {
+            return false;
+        }
+        self.count += 1;
+        self.nodes_left = self.policy.budget(self.count);
+        true
+    }
+
+    /// Records the grid with `contents` as having no fill.
+    pub fn add_nogood(&mut self, contents: &str) {
+        self.nogoods
+            .get_or_insert_with(contents.chars(), |_| (), |_| 0);
+    }
+
+    pub fn is_nogood(&self, contents: &str) -> bool {
+        self.nogoods.contains(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{luby, RestartPolicy, Restarts};
+
+    #[test]
+    fn budgets() {
+        let sequence: Vec<usize> = (1..=15).map(luby).collect();
+        assert_eq!(sequence, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+
+        let luby = RestartPolicy::Luby { base: 100 };
+        assert_eq!(luby.budget(2), 200);
+        let geometric = RestartPolicy::Geometric {
+            initial: 100,
+            factor: 1.5,
+        };
+        assert_eq!(geometric.budget(2), 225);
+    }
+
+    #[test]
+    fn budgets_must_grow() {
+        assert!(RestartPolicy::Luby { base: 1 }.validate().is_ok());
+        assert!(RestartPolicy::Luby { base: 0 }.validate().is_err());
+        for (initial, factor) in [
+            (0, 2.0),
+            (1, 1.0),
+            (8, 0.5),
+            (8, f64::INFINITY),
+            (8, f64::NAN),
+        ] {
+            assert!(RestartPolicy::Geometric { initial, factor }
+                .validate()
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn nogoods_are_exact_and_bounded() {
+        let mut restarts = Restarts::new(RestartPolicy::Luby { base: 1 });
+        restarts.add_nogood("CAT      ");
+        assert!(restarts.is_nogood("CAT      "));
+        assert!(!restarts.is_nogood("COT      "));
+
+        for i in 0..10_000 {
+            restarts.add_nogood(&format!("{:>1000}", i));
+        }
+        assert!(!restarts.is_nogood("CAT      "));
+        assert!(restarts.is_nogood(&format!("{:>1000}", 9_999)));
+    }
+}
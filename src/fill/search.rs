@@ -0,0 +1,529 @@
+//! Resumable depth-first search behind `Filler`.
+//!
+//! `Filler::start` sets up a `FillSearch` and `Filler::step` advances it by a bounded number
+//! of nodes, so callers that cannot block (e.g. a browser event loop) can pump the search
+//! themselves and abandon it at any point.
+
+use std::{iter, sync::atomic::Ordering};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{
+    build_square_to_entry_lookup,
+    cache::WordList,
+    duplicates::{DuplicateRules, UsedEntries},
+    filter::{AllowedWords, WordFilter},
+    get_orthogonal_words, is_valid_grid,
+    letters::LetterRules,
+    order::ValueOrderer,
+    restart::{RestartPolicy, Restarts},
+    select::{next_entry, theme_cells, EntrySelection, EntrySelector, SelectionContext},
+    trail::TrailGrid,
+    EntryLocationToFill, Filler,
+};
+use crate::{
+    crossword::{Direction, EntryLocation},
+    index::{sample_by_weight, shuffle_ties},
+    Crossword,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FillStatus {
+    /// The search has nodes left to explore.
+    Running,
+    Filled(Crossword),
+    /// The search space was exhausted without finding a fill.
+    Failed,
+    Cancelled,
+    /// The fill options could not be used, e.g. an excluded pattern is not a valid regex.
+    Invalid(String),
+}
+
+/// The words left to try for an entry of an expanded grid.
+struct Frame {
+    entry: EntryLocation,
+    /// Every word fitting the entry, as cached.
+    list: WordList,
+    /// Positions in `list` of the valid words left to try, the next one last.
+    words: Vec<usize>,
+    /// Trail mark of the expanded grid.
+    mark: usize,
+    /// Mark of the used entries of the expanded grid.
+    used_mark: usize,
+    /// Contents of the expanded grid, recorded as a nogood once all of its words fail. Only
+    /// set when restarting.
+    key: Option<String>,
+}
+
+/// The state of an in-progress fill. Holds no references, so it can be stored across calls
+/// to `Filler::step` (and across fillers, as long as they share the same `Index`).
+///
+/// The search edits one grid in place: `frames` holds the words still to try at each level,
+/// and backtracking undoes placements through the grid's trail.
+pub struct FillSearch {
+    grid: TrailGrid,
+    frames: Vec<Frame>,
+    /// Whether `grid` still has to be expanded, rather than having its words in `frames`.
+    expand: bool,
+    entry_locations: Vec<EntryLocation>,
+    entry_locations_to_fill: Option<FxHashSet<(Direction, usize, usize)>>,
+    rng: Option<StdRng>,
+    allowed: AllowedWords,
+    /// The complete entries of `grid`, kept in step with it.
+    used: UsedEntries,
+    duplicate_rules: DuplicateRules,
+    letter_rules: LetterRules,
+    restarts: Option<Restarts>,
+    selector: Box<dyn EntrySelector>,
+    orderer: Box<dyn ValueOrderer>,
+    theme_cells: FxHashSet<usize>,
+    status: FillStatus,
+    nodes: usize,
+}
+
+impl FillSearch {
+    pub fn status(&self) -> &FillStatus {
+        &self.status
+    }
+
+    /// Number of partial grids expanded so far.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Number of times the search started over.
+    pub fn restarts(&self) -> usize {
+        self.restarts.as_ref().map_or(0, Restarts::count)
+    }
+
+    /// Chooses entries with `selector` instead of `FillOptions::entry_selection`.
+    pub fn with_entry_selector(mut self, selector: Box<dyn EntrySelector>) -> FillSearch {
+        self.selector = selector;
+        self
+    }
+
+    /// Orders words with `orderer` instead of `FillOptions::value_ordering`.
+    pub fn with_value_orderer(mut self, orderer: Box<dyn ValueOrderer>) -> FillSearch {
+        self.orderer = orderer;
+        self
+    }
+
+    /// Stops the search. Later calls to `Filler::step` return `FillStatus::Cancelled`.
+    pub fn cancel(&mut self) {
+        if self.status == FillStatus::Running {
+            self.status = FillStatus::Cancelled;
+            self.frames.clear();
+        }
+    }
+
+    fn should_fill(&self, entry_location: &EntryLocation) -> bool {
+        match &self.entry_locations_to_fill {
+            None => true,
+            Some(lookup) => lookup.contains(&(
+                entry_location.direction.clone(),
+                entry_location.start_row,
+                entry_location.start_col,
+            )),
+        }
+    }
+}
+
+/// Places the next word left to try in `frames`, backtracking past exhausted frames. Returns
+/// false once the whole tree has been explored.
+fn advance(
+    grid: &mut TrailGrid,
+    used: &mut UsedEntries,
+    frames: &mut Vec<Frame>,
+    restarts: &mut Option<Restarts>,
+    entry_location_lookup: &FxHashMap<(Direction, usize, usize), &EntryLocation>,
+) -> bool {
+    while let Some(frame) = frames.last_mut() {
+        grid.undo(frame.mark);
+        used.undo(frame.used_mark);
+        match frame.words.pop() {
+            Some(i) => {
+                grid.place(&frame.entry, &frame.list[i].0);
+                let crossings = get_orthogonal_words(&frame.entry, entry_location_lookup);
+                used.add_complete(grid.grid(), iter::once(&frame.entry).chain(crossings));
+                return true;
+            }
+            None => {
+                if let (Some(restarts), Some(key)) = (restarts.as_mut(), frame.key.take()) {
+                    restarts.add_nogood(&key);
+                }
+                frames.pop();
+            }
+        }
+    }
+    false
+}
+
+impl<'s> Filler<'s> {
+    /// Prepares a search for `crossword` without expanding any nodes.
+    pub fn start(
+        &self,
+        crossword: &Crossword,
+        entry_locations_to_fill: Option<&Vec<EntryLocationToFill>>,
+    ) -> FillSearch {
+        let rng = match (self.options.randomize, self.options.seed) {
+            (false, Some(seed)) if self.options.restarts.is_some() => {
+                Some(StdRng::seed_from_u64(seed))
+            }
+            (false, None) if self.options.restarts.is_some() => Some(StdRng::seed_from_u64(0)),
+            (false, _) => None,
+            (true, Some(seed)) => Some(StdRng::seed_from_u64(seed)),
+            (true, None) => Some(StdRng::from_entropy()),
+        };
+
+        let entry_locations = crossword.get_entries();
+        let mut search = FillSearch {
+            grid: TrailGrid::new(crossword.to_owned()),
+            frames: vec![],
+            expand: true,
+            used: UsedEntries::new(crossword, &entry_locations),
+            entry_locations,
+            entry_locations_to_fill: entry_locations_to_fill.map(|entry_locations| {
+                entry_locations
+                    .iter()
+                    .map(|e| (e.direction.clone(), e.start_row, e.start_col))
+                    .collect()
+            }),
+            rng,
+            allowed: AllowedWords::default(),
+            duplicate_rules: DuplicateRules::new(&self.options),
+            letter_rules: LetterRules::default(),
+            restarts: self.options.restarts.clone().map(Restarts::new),
+            selector: self.options.entry_selection.selector(),
+            orderer: self.options.value_ordering.orderer(),
+            theme_cells: FxHashSet::default(),
+            status: FillStatus::Running,
+            nodes: 0,
+        };
+
+        search.theme_cells = theme_cells(crossword, &search.entry_locations);
+
+        let mut fillable: Vec<usize> = search
+            .entry_locations
+            .iter()
+            .filter(|entry_location| search.should_fill(entry_location))
+            .flat_map(|entry_location| entry_location.cells())
+            .map(|(row, col)| row * crossword.width + col)
+            .collect();
+        fillable.sort_unstable();
+        fillable.dedup();
+        match (
+            WordFilter::new(&self.options),
+            LetterRules::new(&self.options, fillable),
+        ) {
+            (Ok(filter), Ok(letter_rules)) => {
+                search.allowed = AllowedWords::new(filter);
+                if self.options.entry_selection == EntrySelection::WeightedDomain {
+                    search.allowed.set_weight_floor(self.index);
+                }
+                search.letter_rules = letter_rules;
+            }
+            (Err(e), _) | (_, Err(e)) => search.status = FillStatus::Invalid(e),
+        }
+        if let Some(temperature) = self.options.temperature {
+            if !temperature.is_finite() || temperature <= 0.0 {
+                search.status = FillStatus::Invalid(String::from("Temperature must be positive."));
+            } else if !self.options.randomize {
+                search.status =
+                    FillStatus::Invalid(String::from("Temperature needs randomize to be set."));
+            }
+        }
+        if let Some(Err(e)) = self.options.restarts.as_ref().map(RestartPolicy::validate) {
+            search.status = FillStatus::Invalid(e);
+        }
+        search
+    }
+
+    /// Expands at most `budget` nodes of `search` and returns its status afterwards.
+    pub fn step<'f>(&mut self, search: &'f mut FillSearch, budget: usize) -> &'f FillStatus {
+        if search.status != FillStatus::Running {
+            return &search.status;
+        }
+
+        let entry_location_lookup = build_square_to_entry_lookup(&search.entry_locations);
+
+        for _ in 0..budget {
+            if let Some(cancel) = &self.cancel {
+                if cancel.load(Ordering::Relaxed) {
+                    search.status = FillStatus::Cancelled;
+                    break;
+                }
+            }
+
+            if !search.expand
+                && !advance(
+                    &mut search.grid,
+                    &mut search.used,
+                    &mut search.frames,
+                    &mut search.restarts,
+                    &entry_location_lookup,
+                )
+            {
+                search.status = FillStatus::Failed;
+                break;
+            }
+            search.expand = false;
+            search.nodes += 1;
+
+            let candidate = search.grid.grid();
+            let mut allowed = std::mem::take(&mut search.allowed);
+            let mut context = SelectionContext {
+                allowed: &mut allowed,
+                cache: &mut self.cache,
+                index: self.index,
+                crossings: &entry_location_lookup,
+                theme_cells: &search.theme_cells,
+                width: candidate.width,
+            };
+            let (to_fill_option, entries_left) = next_entry(
+                search.selector.as_ref(),
+                candidate,
+                search
+                    .entry_locations
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, entry_location)| search.should_fill(entry_location)),
+                &mut context,
+            );
+
+            let (to_fill_index, to_fill) = match to_fill_option {
+                Some(to_fill) => to_fill,
+                None => {
+                    search.allowed = allowed;
+                    // Nothing was placed, so the letter rules have not seen this grid yet.
+                    search.status = match search.letter_rules.allows(candidate) {
+                        true => FillStatus::Filled(candidate.clone()),
+                        false => FillStatus::Failed,
+                    };
+                    break;
+                }
+            };
+
+            let words = self.cache.words.words(to_fill.clone(), self.index).clone();
+            let mut potential_fills: Vec<usize> = (0..words.len()).collect();
+            let weight = |i: &usize| words[*i].1;
+            if let Some(rng) = search.rng.as_mut() {
+                match self.options.temperature {
+                    Some(temperature) => {
+                        sample_by_weight(&mut potential_fills, weight, temperature, rng)
+                    }
+                    None => shuffle_ties(&mut potential_fills, weight, rng),
+                }
+            }
+            allowed.apply(&to_fill, &words, &mut potential_fills);
+            let to_fill = &search.entry_locations[to_fill_index];
+            let width = search.grid.grid().width;
+            search.orderer.order(
+                &mut search.grid,
+                to_fill,
+                &words,
+                &mut potential_fills,
+                &mut SelectionContext {
+                    allowed: &mut allowed,
+                    cache: &mut self.cache,
+                    index: self.index,
+                    crossings: &entry_location_lookup,
+                    theme_cells: &search.theme_cells,
+                    width,
+                },
+            );
+            allowed.filter.prefer_desired(&words, &mut potential_fills);
+
+            // The placed word and its crossings are the only entries that can change.
+            let mut changed_entries = vec![to_fill];
+            changed_entries.extend(get_orthogonal_words(to_fill, &entry_location_lookup));
+
+            let mark = search.grid.mark();
+            let mut valid_fills = vec![];
+            for potential_fill in potential_fills {
+                search.grid.place(to_fill, &words[potential_fill].0);
+                let new_candidate = search.grid.grid();
+
+                let valid = is_valid_grid(
+                    new_candidate,
+                    &changed_entries,
+                    self.index,
+                    &mut search.used,
+                    &search.duplicate_rules,
+                    &mut self.cache,
+                    &mut allowed,
+                );
+
+                if valid && search.letter_rules.allows(new_candidate) {
+                    if entries_left == 1 || !new_candidate.contents.contains(' ') {
+                        search.status = FillStatus::Filled(new_candidate.clone());
+                        break;
+                    }
+                    let nogood = search
+                        .restarts
+                        .as_ref()
+                        .is_some_and(|restarts| restarts.is_nogood(&new_candidate.contents));
+                    if !nogood {
+                        valid_fills.push(potential_fill);
+                    }
+                }
+                search.grid.undo(mark);
+            }
+            search.allowed = allowed;
+            if search.status != FillStatus::Running {
+                break;
+            }
+
+            // Frames pop the last word first, so store them best-last.
+            valid_fills.reverse();
+            search.frames.push(Frame {
+                entry: to_fill.clone(),
+                list: words,
+                words: valid_fills,
+                mark,
+                used_mark: search.used.mark(),
+                key: search
+                    .restarts
+                    .as_ref()
+                    .map(|_| search.grid.grid().contents.clone()),
+            });
+
+            if search.restarts.as_mut().is_some_and(Restarts::expanded) {
+                search.grid.undo(0);
+                search.used.undo(0);
+                search.frames.clear();
+                search.expand = true;
+            }
+        }
+
+        if search.status != FillStatus::Running {
+            search.frames.clear();
+        }
+        &search.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FillStatus;
+    use crate::{
+        fill::{restart::RestartPolicy, Fill, FillOptions, Filler},
+        index::Index,
+        Crossword,
+    };
+
+    #[test]
+    fn stepping_matches_fill() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let options = FillOptions {
+            seed: Some(3),
+            ..FillOptions::default()
+        };
+
+        let mut filler = Filler::with_options(&index, &options);
+        let expected = filler.fill(&grid, None).unwrap();
+
+        let mut search = filler.start(&grid, None);
+        let mut steps = 0;
+        while *filler.step(&mut search, 1) == FillStatus::Running {
+            steps += 1;
+        }
+
+        assert_eq!(search.status(), &FillStatus::Filled(expected));
+        assert_eq!(search.nodes(), steps + 1);
+    }
+
+    #[test]
+    fn best_words_first() {
+        // Both fills are valid; the heavier one must be found first.
+        let index = Index::build(vec![
+            (String::from("AB"), 10),
+            (String::from("CD"), 10),
+            (String::from("AC"), 10),
+            (String::from("BD"), 10),
+            (String::from("XY"), 1),
+            (String::from("ZW"), 1),
+            (String::from("XZ"), 1),
+            (String::from("YW"), 1),
+        ]);
+        let grid = Crossword::from_string(String::from("    "), 2, 2).unwrap();
+
+        let filled = Filler::new(&index).fill(&grid, None).unwrap();
+        assert!(!filled.contents.contains(|c| "WXYZ".contains(c)));
+    }
+
+    #[test]
+    fn restarts() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let options = FillOptions {
+            randomize: false,
+            restarts: Some(RestartPolicy::Luby { base: 2 }),
+            ..FillOptions::default()
+        };
+        let mut filler = Filler::with_options(&index, &options);
+        let mut search = filler.start(&grid, None);
+        while *filler.step(&mut search, 100) == FillStatus::Running {}
+
+        match search.status() {
+            FillStatus::Filled(filled) => assert!(!filled.contents.contains(' ')),
+            status => panic!("Unexpected status {:?}", status),
+        }
+        assert!(search.restarts() > 0);
+
+        // Nogoods let a restarting search still prove there is no fill.
+        let index = Index::build(vec![
+            (String::from("BIT"), 0),
+            (String::from("ICE"), 0),
+            (String::from("TEN"), 0),
+        ]);
+        let grid = Crossword::from_string(String::from("         "), 3, 3).unwrap();
+        let mut filler = Filler::with_options(&index, &options);
+        assert!(filler.fill(&grid, None).is_err());
+
+        // Even with the smallest budgets that keep growing.
+        for policy in [
+            RestartPolicy::Luby { base: 1 },
+            RestartPolicy::Geometric {
+                initial: 1,
+                factor: 1.1,
+            },
+        ] {
+            let options = FillOptions {
+                restarts: Some(policy),
+                ..options.clone()
+            };
+            let mut filler = Filler::with_options(&index, &options);
+            let mut search = filler.start(&grid, None);
+            while *filler.step(&mut search, 1000) == FillStatus::Running {}
+            assert_eq!(search.status(), &FillStatus::Failed);
+        }
+
+        // Budgets that never grow are rejected up front.
+        let options = FillOptions {
+            restarts: Some(RestartPolicy::Luby { base: 0 }),
+            ..options
+        };
+        let mut filler = Filler::with_options(&index, &options);
+        let mut search = filler.start(&grid, None);
+        assert!(matches!(
+            filler.step(&mut search, 1000),
+            FillStatus::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn cancelled_search_stops() {
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        let index = Index::build_default();
+        let mut filler = Filler::new(&index);
+
+        let mut search = filler.start(&grid, None);
+        filler.step(&mut search, 1);
+        search.cancel();
+
+        assert_eq!(filler.step(&mut search, 100), &FillStatus::Cancelled);
+        assert_eq!(search.nodes(), 1);
+    }
+}
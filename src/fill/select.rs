@@ -0,0 +1,307 @@
+//! Choosing which entry a search fills next.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+use super::{cache::FillCache, filter::AllowedWords};
+use crate::{
+    crossword::{Direction, EntryIterator, EntryLocation},
+    index::Index,
+    Crossword,
+};
+
+/// Ranks the entries of a partial grid that still have blank cells.
+pub trait EntrySelector {
+    /// The entry with the lowest key is filled next, ties going to the one starting first.
+    fn key(&self, entry: &EntryIterator, context: &mut SelectionContext) -> (i64, i64);
+}
+
+/// The built-in `EntrySelector`s, chosen by `FillOptions::entry_selection`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum EntrySelection {
+    /// Fewest candidate words first.
+    #[default]
+    Mrv,
+    /// Fewest candidate words first, then most blank cells with a crossing.
+    MrvDegree,
+    /// Longest entries first, then fewest candidate words.
+    LongestFirst,
+    /// Entries crossing a theme entry first, then fewest candidate words. Theme entries are
+    /// the locked ones, or the prefilled ones if nothing is locked.
+    ThemeAdjacentFirst,
+    /// Lowest `AllowedWords::weighted_count` first, so entries with only poor words left are
+    /// settled before they run out.
+    WeightedDomain,
+}
+
+impl EntrySelection {
+    pub fn selector(self) -> Box<dyn EntrySelector> {
+        match self {
+            EntrySelection::Mrv => Box::new(Mrv),
+            EntrySelection::MrvDegree => Box::new(MrvDegree),
+            EntrySelection::LongestFirst => Box::new(LongestFirst),
+            EntrySelection::ThemeAdjacentFirst => Box::new(ThemeAdjacentFirst),
+            EntrySelection::WeightedDomain => Box::new(WeightedDomain),
+        }
+    }
+}
+
+/// What an `EntrySelector` may consult about the grid being searched.
+pub struct SelectionContext<'a, 's> {
+    pub allowed: &'a mut AllowedWords,
+    pub cache: &'a mut FillCache,
+    pub index: &'a Index,
+    pub(crate) crossings: &'a FxHashMap<(Direction, usize, usize), &'s EntryLocation>,
+    pub(crate) theme_cells: &'a FxHashSet<usize>,
+    pub(crate) width: usize,
+}
+
+impl<'a, 's> SelectionContext<'a, 's> {
+    /// Number of words the entry may take.
+    pub fn count(&mut self, entry: &EntryIterator) -> usize {
+        self.allowed.count(entry, self.cache, self.index)
+    }
+
+    pub fn weighted_count(&mut self, entry: &EntryIterator) -> u64 {
+        self.allowed.weighted_count(entry, self.cache, self.index)
+    }
+
+    /// Number of blank cells of the entry that another entry crosses.
+    pub fn open_crossings(&self, entry: &EntryIterator) -> usize {
+        let direction = match entry.entry_location.direction {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        };
+        entry
+            .entry_location
+            .cells()
+            .zip(entry.clone())
+            .filter(|(_, c)| *c == ' ')
+            .filter(|((row, col), _)| {
+                self.crossings
+                    .get(&(direction.clone(), *row, *col))
+                    .is_some_and(|crossing| crossing.length > 1)
+            })
+            .count()
+    }
+
+    /// Whether the entry shares a cell with a theme entry.
+    pub fn is_theme_adjacent(&self, entry: &EntryIterator) -> bool {
+        entry
+            .entry_location
+            .cells()
+            .any(|(row, col)| self.theme_cells.contains(&(row * self.width + col)))
+    }
+}
+
+/// The entry of `grid` a search fills next: among `entries` that still have blank cells, the
+/// one `selector` ranks lowest, ties going to the one starting first. Also returns how many
+/// entries have blank cells.
+pub fn next_entry<'g>(
+    selector: &dyn EntrySelector,
+    grid: &'g Crossword,
+    entries: impl Iterator<Item = (usize, &'g EntryLocation)>,
+    context: &mut SelectionContext,
+) -> (Option<(usize, EntryIterator<'g>)>, usize) {
+    let mut entries_left = 0;
+    let next = entries
+        .map(|(i, entry_location)| (i, EntryIterator::new(grid, entry_location)))
+        .filter(|(_, iter)| iter.clone().any(|c| c == ' '))
+        .inspect(|_| entries_left += 1)
+        .min_by_key(|(_, iter)| {
+            (
+                selector.key(iter, context),
+                iter.entry_location.start_row,
+                iter.entry_location.start_col,
+            )
+        });
+    (next, entries_left)
+}
+
+/// The cells of the theme entries of `crossword`, see `EntrySelection::ThemeAdjacentFirst`.
+pub fn theme_cells(crossword: &Crossword, entry_locations: &[EntryLocation]) -> FxHashSet<usize> {
+    let locked: FxHashSet<usize> = (0..crossword.width * crossword.height)
+        .filter(|i| crossword.is_cell_locked(i / crossword.width, i % crossword.width))
+        .collect();
+    if !locked.is_empty() {
+        return locked;
+    }
+    entry_locations
+        .iter()
+        .filter(|entry_location| entry_location.prefilled && entry_location.length > 1)
+        .flat_map(|entry_location| entry_location.cells())
+        .map(|(row, col)| row * crossword.width + col)
+        .collect()
+}
+
+pub struct Mrv;
+
+impl EntrySelector for Mrv {
+    fn key(&self, entry: &EntryIterator, context: &mut SelectionContext) -> (i64, i64) {
+        (context.count(entry) as i64, 0)
+    }
+}
+
+pub struct MrvDegree;
+
+impl EntrySelector for MrvDegree {
+    fn key(&self, entry: &EntryIterator, context: &mut SelectionContext) -> (i64, i64) {
+        (
+            context.count(entry) as i64,
+            -(context.open_crossings(entry) as i64),
+        )
+    }
+}
+
+pub struct LongestFirst;
+
+impl EntrySelector for LongestFirst {
+    fn key(&self, entry: &EntryIterator, context: &mut SelectionContext) -> (i64, i64) {
+        (
+            -(entry.entry_location.length as i64),
+            context.count(entry) as i64,
+        )
+    }
+}
+
+pub struct ThemeAdjacentFirst;
+
+impl EntrySelector for ThemeAdjacentFirst {
+    fn key(&self, entry: &EntryIterator, context: &mut SelectionContext) -> (i64, i64) {
+        (
+            !context.is_theme_adjacent(entry) as i64,
+            context.count(entry) as i64,
+        )
+    }
+}
+
+pub struct WeightedDomain;
+
+impl EntrySelector for WeightedDomain {
+    fn key(&self, entry: &EntryIterator, context: &mut SelectionContext) -> (i64, i64) {
+        (
+            context.weighted_count(entry) as i64,
+            context.count(entry) as i64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_entry, theme_cells, EntrySelection, SelectionContext};
+    use crate::{
+        crossword::Direction,
+        fill::{
+            build_square_to_entry_lookup, cache::FillCache, filter::AllowedWords, Fill,
+            FillOptions, Filler,
+        },
+        index::Index,
+        Crossword,
+    };
+
+    /// The entry a search using `selection` fills first.
+    fn first_pick(
+        selection: EntrySelection,
+        grid: &Crossword,
+        index: &Index,
+    ) -> (Direction, usize, usize) {
+        let entries = grid.get_entries();
+        let crossings = build_square_to_entry_lookup(&entries);
+        let theme_cells = theme_cells(grid, &entries);
+        let mut cache = FillCache::default();
+        let mut allowed = AllowedWords::default();
+        let mut context = SelectionContext {
+            allowed: &mut allowed,
+            cache: &mut cache,
+            index,
+            crossings: &crossings,
+            theme_cells: &theme_cells,
+            width: grid.width,
+        };
+        let selector = selection.selector();
+        let (next, _) = next_entry(
+            selector.as_ref(),
+            grid,
+            entries.iter().enumerate(),
+            &mut context,
+        );
+        let entry = next.unwrap().1.entry_location;
+        (entry.direction.clone(), entry.start_row, entry.start_col)
+    }
+
+    #[test]
+    fn picks() {
+        let words =
+            |words: &[&str]| Index::build(words.iter().map(|w| (w.to_string(), 0)).collect());
+        // Two columns and three rows: the acrosses are short and the downs long.
+        let grid = Crossword::from_string(String::from("      "), 2, 3).unwrap();
+
+        let index = words(&["AB", "CD", "ACE", "BDF", "XYZ"]);
+        assert_eq!(
+            first_pick(EntrySelection::Mrv, &grid, &index),
+            (Direction::Across, 0, 0)
+        );
+        assert_eq!(
+            first_pick(EntrySelection::LongestFirst, &grid, &index),
+            (Direction::Down, 0, 0)
+        );
+
+        // With as many short words as long ones, the downs win on crossings.
+        let index = words(&["AB", "CD", "ACE", "BDF"]);
+        assert_eq!(
+            first_pick(EntrySelection::Mrv, &grid, &index),
+            (Direction::Across, 0, 0)
+        );
+        assert_eq!(
+            first_pick(EntrySelection::MrvDegree, &grid, &index),
+            (Direction::Down, 0, 0)
+        );
+
+        // The Q constrains the top left, but only the entries through the locked cell are
+        // theme-adjacent.
+        let index = Index::build_default();
+        let mut grid = Crossword::from_string(String::from("Q        "), 3, 3).unwrap();
+        grid.lock_cell(2, 2).unwrap();
+        assert_eq!(
+            first_pick(EntrySelection::Mrv, &grid, &index),
+            (Direction::Across, 0, 0)
+        );
+        assert_eq!(
+            first_pick(EntrySelection::ThemeAdjacentFirst, &grid, &index),
+            (Direction::Down, 0, 2)
+        );
+    }
+
+    #[test]
+    fn selections() {
+        let index = Index::build_default();
+        let grid = Crossword::from_string(String::from("                    "), 5, 4).unwrap();
+        for entry_selection in [
+            EntrySelection::Mrv,
+            EntrySelection::MrvDegree,
+            EntrySelection::LongestFirst,
+            EntrySelection::ThemeAdjacentFirst,
+            EntrySelection::WeightedDomain,
+        ] {
+            let options = FillOptions {
+                seed: Some(1),
+                entry_selection,
+                ..FillOptions::default()
+            };
+            let filled = Filler::with_options(&index, &options)
+                .fill(&grid, None)
+                .unwrap();
+            assert!(!filled.contents.contains(' '), "{:?}", entry_selection);
+        }
+
+        // Prefilled entries are the theme unless something is locked.
+        let mut grid = Crossword::from_string(String::from("CAT      "), 3, 3).unwrap();
+        let entries = grid.get_entries();
+        assert_eq!(theme_cells(&grid, &entries).len(), 3);
+        grid.lock_cell(2, 2).unwrap();
+        assert_eq!(
+            theme_cells(&grid, &entries).into_iter().collect::<Vec<_>>(),
+            vec![8]
+        );
+    }
+}
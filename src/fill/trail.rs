@@ -0,0 +1,79 @@
+//! One grid edited in place by a search, instead of a copy per candidate.
+//!
+//! Every cell a placement overwrites is recorded on a trail, so backtracking only has to
+//! replay the trail back to an earlier mark.
+
+use crate::{crossword::EntryLocation, Crossword};
+
+pub struct TrailGrid {
+    grid: Crossword,
+    trail: Vec<(usize, u8)>,
+}
+
+impl TrailGrid {
+    pub fn new(grid: Crossword) -> TrailGrid {
+        TrailGrid {
+            grid,
+            trail: vec![],
+        }
+    }
+
+    pub fn grid(&self) -> &Crossword {
+        &self.grid
+    }
+
+    /// The point to `undo` back to in order to drop the placements made after this call.
+    pub fn mark(&self) -> usize {
+        self.trail.len()
+    }
+
+    /// Writes `word` over the cells of `entry_location`.
+    pub fn place(&mut self, entry_location: &EntryLocation, word: &str) {
+        for ((row, col), c) in entry_location.cells().zip(word.bytes()) {
+            let index = row * self.grid.width + col;
+            let previous = self.grid.contents.as_bytes()[index];
+            if previous != c {
+                self.trail.push((index, previous));
+                self.grid.set_cell(index, c);
+            }
+        }
+    }
+
+    /// Restores the grid to how it was at `mark`.
+    pub fn undo(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let (index, previous) = self.trail.pop().unwrap();
+            self.grid.set_cell(index, previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrailGrid;
+    use crate::{crossword::Direction, Crossword};
+
+    #[test]
+    fn place_and_undo() {
+        let grid = Crossword::from_string(String::from("C  A  T  "), 3, 3).unwrap();
+        let entries = grid.get_entries();
+        let find = |direction: Direction, col: usize| {
+            entries
+                .iter()
+                .find(|e| e.direction == direction && e.start_row == 0 && e.start_col == col)
+                .unwrap()
+        };
+        let mut trail = TrailGrid::new(grid.clone());
+
+        let start = trail.mark();
+        trail.place(find(Direction::Across, 0), "COT");
+        let placed = trail.mark();
+        trail.place(find(Direction::Down, 1), "OAR");
+        assert_eq!(trail.grid().contents, "COTAA TR ");
+
+        trail.undo(placed);
+        assert_eq!(trail.grid().contents, "COTA  T  ");
+        trail.undo(start);
+        assert_eq!(trail.grid(), &grid);
+    }
+}
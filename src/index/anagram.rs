@@ -0,0 +1,174 @@
+//! Letter-bank searches over the trie.
+//!
+//! Letters outside `A`-`Z` are ignored, except that `?` is a blank tile standing for any
+//! letter.
+
+use super::{Index, Trie, TrieNode};
+use crate::WeightedWord;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Uses every tile.
+    Exact,
+    /// Uses some of the tiles.
+    Subset,
+    /// Uses every letter at least once, plus any others.
+    Superset,
+}
+
+#[derive(Debug, Clone)]
+struct Bank {
+    counts: [u32; 26],
+    blanks: usize,
+}
+
+impl Bank {
+    fn new(letters: &str) -> Bank {
+        let mut bank = Bank {
+            counts: [0; 26],
+            blanks: 0,
+        };
+        for c in letters.chars().map(|c| c.to_ascii_uppercase()) {
+            match c {
+                'A'..='Z' => bank.counts[c as usize - 'A' as usize] += 1,
+                '?' => bank.blanks += 1,
+                _ => {}
+            }
+        }
+        bank
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blanks == 0 && self.counts.iter().all(|count| *count == 0)
+    }
+}
+
+impl Trie {
+    fn bank_words(
+        &self,
+        node: &TrieNode,
+        bank: &mut Bank,
+        mode: Mode,
+        partial: &mut String,
+        result: &mut Vec<WeightedWord>,
+    ) {
+        if node.terminal {
+            let matched = match mode {
+                Mode::Subset => true,
+                Mode::Exact | Mode::Superset => bank.is_empty(),
+            };
+            if matched {
+                result.push(node.weighted_word(partial));
+            }
+        }
+
+        for child in self.children(node) {
+            let slot = match child.letter {
+                b'A'..=b'Z' => Some((child.letter - b'A') as usize),
+                _ => None,
+            };
+
+            // Prefer a real tile over a blank. A superset word may also use letters freely once
+            // its blanks are spent.
+            let used_tile = match slot {
+                Some(slot) if bank.counts[slot] > 0 => {
+                    bank.counts[slot] -= 1;
+                    Some(slot)
+                }
+                _ => None,
+            };
+            let used_blank = used_tile.is_none() && bank.blanks > 0;
+            if used_tile.is_none() && !used_blank && mode != Mode::Superset {
+                continue;
+            }
+            if used_blank {
+                bank.blanks -= 1;
+            }
+
+            partial.push(child.letter as char);
+            self.bank_words(child, bank, mode, partial, result);
+            partial.pop();
+
+            if let Some(slot) = used_tile {
+                bank.counts[slot] += 1;
+            }
+            if used_blank {
+                bank.blanks += 1;
+            }
+        }
+    }
+}
+
+impl Index {
+    /// Words using exactly the given letters, highest weight first.
+    pub fn anagrams(&self, letters: &str) -> Vec<WeightedWord> {
+        self.bank_words(letters, Mode::Exact)
+    }
+
+    /// Words that can be spelled from some of the given letters, longest first and then by
+    /// weight.
+    pub fn sub_anagrams(&self, letters: &str) -> Vec<WeightedWord> {
+        let mut result = self.bank_words(letters, Mode::Subset);
+        result.sort_by_key(|w| std::cmp::Reverse(w.word.len()));
+        result
+    }
+
+    /// Words containing all of the given letters in any order, highest weight first. Each `?`
+    /// asks for one more letter of any kind.
+    pub fn words_containing_letters(&self, letters: &str) -> Vec<WeightedWord> {
+        self.bank_words(letters, Mode::Superset)
+    }
+
+    fn bank_words(&self, letters: &str, mode: Mode) -> Vec<WeightedWord> {
+        let mut bank = Bank::new(letters);
+        let mut result = vec![];
+        let mut partial = String::new();
+        self.trie
+            .bank_words(self.trie.root(), &mut bank, mode, &mut partial, &mut result);
+
+        result.sort_unstable_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        result.into_iter().map(|w| self.with_display(w)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{index::Index, WeightedWord};
+
+    fn words(result: Vec<WeightedWord>) -> Vec<String> {
+        result.into_iter().map(|w| w.word).collect()
+    }
+
+    #[test]
+    fn letter_banks() {
+        let index = Index::build(vec![
+            (String::from("STAR"), 1),
+            (String::from("RATS"), 2),
+            (String::from("ARTS"), 0),
+            (String::from("TSAR"), 0),
+            (String::from("RAT"), 0),
+            (String::from("STARE"), 0),
+            (String::from("TREAT"), 0),
+        ]);
+
+        assert_eq!(
+            words(index.anagrams("star")),
+            vec!["RATS", "STAR", "ARTS", "TSAR"]
+        );
+        assert_eq!(words(index.anagrams("RAT?")).len(), 4);
+        assert_eq!(
+            words(index.sub_anagrams("STARE")),
+            vec!["STARE", "RATS", "STAR", "ARTS", "TSAR", "RAT"]
+        );
+        assert_eq!(words(index.words_containing_letters("TTA")), vec!["TREAT"]);
+        assert_eq!(
+            words(index.words_containing_letters("TS?")),
+            vec!["RATS", "STAR", "ARTS", "STARE", "TSAR"]
+        );
+        assert_eq!(
+            words(index.words_containing_letters("RAT??")),
+            vec!["STARE", "TREAT"]
+        );
+        assert!(index.anagrams(&"A".repeat(300)).is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+//! Compact binary encoding of an `Index`.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! magic      [u8; 4]  b"XWIX"
+//! version    u16
+//! reserved   u16
+//! node_count u32
+//! length     u32      payload length in bytes
+//! checksum   u32      CRC-32 of the payload
+//! payload    nodes in the order of the trie's node table, then display text
+//! ```
+//!
+//! The trie's nodes are written breadth first, as `Trie` lays them out: the root first, and
+//! the children of each node together and sorted by letter. Each node is `letter: u8`
+//! (ignored for the root), `flags: u8` (bit 0 terminal), `weight: i32` if terminal and
+//! `child_count: u32`. Where a node's children start is not stored: decoding copies the nodes
+//! into a new table and works it out from the counts of the nodes before it.
+//!
+//! The nodes are followed by `display_count: u32` entries of `word_len: u32`, the word,
+//! `display_len: u32` and the UTF-8 display text.
+//!
+//! Tries deeper than `MAX_DEPTH` are rejected when decoding.
+
+use rustc_hash::FxHashMap;
+use std::convert::TryFrom;
+
+use super::{Trie, TrieNode};
+
+const MAGIC: &[u8; 4] = b"XWIX";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 20;
+
+const FLAG_TERMINAL: u8 = 1;
+
+pub(crate) fn encode(trie: &Trie, display: &FxHashMap<String, String>) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(trie.nodes.len() * 6);
+    for node in trie.nodes.iter() {
+        payload.push(node.letter);
+        match node.terminal {
+            true => {
+                payload.push(FLAG_TERMINAL);
+                payload.extend_from_slice(&node.weight.to_le_bytes());
+            }
+            false => payload.push(0),
+        }
+        payload.extend_from_slice(&node.child_count.to_le_bytes());
+    }
+
+    // Sorted so equal indexes encode to equal bytes.
+    let mut display: Vec<(&String, &String)> = display.iter().collect();
+    display.sort_unstable();
+    payload.extend_from_slice(&(display.len() as u32).to_le_bytes());
+    for (word, text) in display {
+        payload.extend_from_slice(&(word.len() as u32).to_le_bytes());
+        payload.extend_from_slice(word.as_bytes());
+        payload.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        payload.extend_from_slice(text.as_bytes());
+    }
+
+    let mut result = Vec::with_capacity(HEADER_LEN + payload.len());
+    result.extend_from_slice(MAGIC);
+    result.extend_from_slice(&VERSION.to_le_bytes());
+    result.extend_from_slice(&0u16.to_le_bytes());
+    result.extend_from_slice(&(trie.nodes.len() as u32).to_le_bytes());
+    result.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    result.extend_from_slice(&crc32(&payload).to_le_bytes());
+    result.extend_from_slice(&payload);
+    result
+}
+
+pub(crate) fn is_index(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Decodes the trie and the display text of its words.
+pub(crate) fn decode(bytes: &[u8]) -> Result<(Trie, FxHashMap<String, String>), String> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(String::from("Not an index file."));
+    }
+
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        return Err(format!("Unsupported index version {}.", version));
+    }
+
+    let node_count = read_u32(bytes, 8) as usize;
+    let length = read_u32(bytes, 12) as usize;
+    let checksum = read_u32(bytes, 16);
+
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() != length {
+        return Err(String::from("Index file is truncated."));
+    }
+    if crc32(payload) != checksum {
+        return Err(String::from("Index checksum does not match."));
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        position: 0,
+    };
+    let trie = reader.trie(node_count)?;
+    let display = reader.display()?;
+
+    if reader.position != payload.len() {
+        return Err(malformed());
+    }
+    Ok((trie, display))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn malformed() -> String {
+    String::from("Index payload is malformed.")
+}
+
+struct Reader<'s> {
+    bytes: &'s [u8],
+    position: usize,
+}
+
+impl<'s> Reader<'s> {
+    fn take(&mut self, len: usize) -> Result<&'s [u8], String> {
+        let end = self.position.checked_add(len).ok_or_else(malformed)?;
+        let result = self.bytes.get(self.position..end).ok_or_else(malformed)?;
+        self.position = end;
+        Ok(result)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut result = [0; N];
+        result.copy_from_slice(self.take(N)?);
+        Ok(result)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn trie(&mut self, node_count: usize) -> Result<Trie, String> {
+        // Every node takes at least 6 bytes, so a bogus count cannot reserve much.
+        if node_count > self.bytes.len() / 6 {
+            return Err(malformed());
+        }
+        let mut nodes = Vec::with_capacity(node_count);
+        for _ in 0..node_count {
+            let letter = self.byte()?;
+            let terminal = self.byte()? & FLAG_TERMINAL != 0;
+            let weight = match terminal {
+                true => i32::from_le_bytes(self.array()?),
+                false => 0,
+            };
+            nodes.push(TrieNode {
+                letter,
+                terminal,
+                weight,
+                first_child: 0,
+                child_count: u32::from_le_bytes(self.array()?),
+            });
+        }
+        Trie::try_from(nodes).map_err(|_| malformed())
+    }
+
+    fn display(&mut self) -> Result<FxHashMap<String, String>, String> {
+        let count = u32::from_le_bytes(self.array()?) as usize;
+        let mut display = FxHashMap::default();
+        for _ in 0..count {
+            let len = u32::from_le_bytes(self.array()?) as usize;
+            let word = String::from_utf8_lossy(self.take(len)?).into_owned();
+            let len = u32::from_le_bytes(self.array()?) as usize;
+            let text = String::from_utf8_lossy(self.take(len)?).into_owned();
+            display.insert(word, text);
+        }
+        Ok(display)
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in bytes {
+        crc = CRC32_TABLE[((crc ^ *b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, decode};
+    use crate::index::{Index, MAX_DEPTH};
+
+    #[test]
+    fn crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn rejects_deep_tries() {
+        let word = "A".repeat(MAX_DEPTH);
+        let index = Index::build(vec![(word.clone(), 0)]);
+        assert!(decode(&index.to_bytes()).is_ok());
+
+        let index = Index::build(vec![(word + "A", 0)]);
+        assert!(decode(&index.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn any_byte_is_a_letter() {
+        // More than 255 children under one node, and a zero byte inside a word.
+        let mut words: Vec<(String, i32)> = (0..300).map(|i| (format!("{:03}", i), i)).collect();
+        words.push((String::from("A\0B"), 7));
+        let index = Index::build(words);
+
+        let loaded = Index::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(loaded.get("299").unwrap().weight, 299);
+        assert_eq!(loaded.words("   ".chars()).len(), 301);
+        assert_eq!(loaded.get("A\0B").unwrap().weight, 7);
+        assert!(loaded.get("AB").is_none());
+    }
+
+    #[test]
+    fn long_display_text() {
+        let word = "A".repeat(MAX_DEPTH);
+        let text = "é".repeat(40_000);
+        let mut index = Index::build(vec![(word.clone(), 1)]);
+        index.set_display(&word, &text);
+
+        let loaded = Index::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(loaded.display(&word), Some(text.as_str()));
+    }
+}
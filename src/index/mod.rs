@@ -0,0 +1,584 @@
+use rand::{prelude::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fs, fs::File, io::BufRead, io::BufReader, path::Path};
+
+use rustc_hash::FxHashMap;
+
+use crate::WeightedWord;
+
+pub mod anagram;
+mod binary;
+pub mod query;
+
+/// A node of a `Trie`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct TrieNode {
+    /// The letter leading to this node from its parent, unused for the root.
+    letter: u8,
+    terminal: bool,
+    weight: i32,
+    /// Position of the first child in `Trie::nodes`, worked out again when loading.
+    #[serde(skip)]
+    first_child: u32,
+    child_count: u32,
+}
+
+impl TrieNode {
+    fn weighted_word(&self, word: &str) -> WeightedWord {
+        WeightedWord {
+            word: String::from(word),
+            weight: self.weight,
+            display: None,
+        }
+    }
+}
+
+/// A trie kept as one table of nodes. The children of a node sit next to each other in
+/// `nodes`, sorted by letter, and the root comes first, so the table can be searched as it
+/// is and written out without pointers.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(try_from = "Vec<TrieNode>", into = "Vec<TrieNode>")]
+pub struct Trie {
+    nodes: Vec<TrieNode>,
+}
+
+/// Deepest node a loaded trie may have, which bounds the recursion of lookups on crafted
+/// input.
+const MAX_DEPTH: usize = 1024;
+
+impl TryFrom<Vec<TrieNode>> for Trie {
+    type Error = String;
+
+    /// Lays out `nodes`, listed breadth first as in `Trie::nodes`, working out where the
+    /// children of each node start from the child counts of the nodes before it. Fails unless
+    /// the counts account for exactly the nodes given, the children of each node are sorted by
+    /// letter and no node is deeper than `MAX_DEPTH`.
+    fn try_from(mut nodes: Vec<TrieNode>) -> Result<Trie, String> {
+        let malformed = || String::from("Trie is malformed.");
+        let node_count = nodes.len();
+        if node_count == 0 {
+            return Err(malformed());
+        }
+
+        // Where the children of the next node start, and the nodes ending the current and
+        // the next level.
+        let mut next_child: usize = 1;
+        let mut level_end = 1;
+        let mut next_level_end = 1;
+        let mut depth = 0;
+        for (i, node) in nodes.iter_mut().enumerate() {
+            if i == level_end {
+                level_end = next_level_end;
+                depth += 1;
+            }
+            if node.child_count > 0 && depth == MAX_DEPTH {
+                return Err(malformed());
+            }
+            node.first_child = next_child as u32;
+            next_child = next_child
+                .checked_add(node.child_count as usize)
+                .filter(|end| *end <= node_count)
+                .ok_or_else(malformed)?;
+            next_level_end = next_child;
+        }
+        if next_child != node_count {
+            return Err(malformed());
+        }
+
+        let trie = Trie { nodes };
+        let sorted = trie.nodes.iter().all(|node| {
+            trie.children(node)
+                .windows(2)
+                .all(|pair| pair[0].letter < pair[1].letter)
+        });
+        match sorted {
+            true => Ok(trie),
+            false => Err(malformed()),
+        }
+    }
+}
+
+impl From<Trie> for Vec<TrieNode> {
+    fn from(trie: Trie) -> Vec<TrieNode> {
+        trie.nodes
+    }
+}
+
+impl Trie {
+    /// Builds the trie of `words`. A word listed more than once keeps its last weight.
+    fn build(mut words: Vec<(String, i32)>) -> Trie {
+        // Stable, so the last of several equal words ends up last.
+        words.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+        let mut nodes = vec![TrieNode {
+            letter: 0,
+            terminal: false,
+            weight: 0,
+            first_child: 1,
+            child_count: 0,
+        }];
+        // Nodes are laid out breadth first, so the children of each node are added together.
+        // Each node covers the words sharing its prefix, a range of the sorted list.
+        let mut ranges = vec![(0, words.len(), 0)];
+        let mut next = 0;
+        while next < nodes.len() {
+            let (mut start, end, depth) = ranges[next];
+            while start < end && words[start].0.len() == depth {
+                nodes[next].terminal = true;
+                nodes[next].weight = words[start].1;
+                start += 1;
+            }
+
+            nodes[next].first_child = nodes.len() as u32;
+            while start < end {
+                let letter = words[start].0.as_bytes()[depth];
+                let child_end = start
+                    + words[start..end]
+                        .iter()
+                        .take_while(|(word, _)| word.as_bytes()[depth] == letter)
+                        .count();
+                nodes.push(TrieNode {
+                    letter,
+                    terminal: false,
+                    weight: 0,
+                    first_child: 0,
+                    child_count: 0,
+                });
+                ranges.push((start, child_end, depth + 1));
+                nodes[next].child_count += 1;
+                start = child_end;
+            }
+            next += 1;
+        }
+        Trie { nodes }
+    }
+
+    fn root(&self) -> &TrieNode {
+        &self.nodes[0]
+    }
+
+    fn children(&self, node: &TrieNode) -> &[TrieNode] {
+        let first = node.first_child as usize;
+        &self.nodes[first..first + node.child_count as usize]
+    }
+
+    fn child(&self, node: &TrieNode, c: char) -> Option<&TrieNode> {
+        let letter = u8::try_from(c).ok()?;
+        let children = self.children(node);
+        children
+            .binary_search_by_key(&letter, |child| child.letter)
+            .ok()
+            .map(|i| &children[i])
+    }
+
+    fn min_weight(&self) -> Option<i32> {
+        self.nodes
+            .iter()
+            .filter(|node| node.terminal)
+            .map(|node| node.weight)
+            .min()
+    }
+
+    fn find(&self, word: &str) -> Option<&TrieNode> {
+        let mut node = self.root();
+        for c in word.chars() {
+            node = self.child(node, c)?;
+        }
+        Some(node)
+    }
+
+    fn fill_words<T: Iterator<Item = char> + Clone>(
+        &self,
+        node: &TrieNode,
+        mut pattern: T,
+        partial: &mut String,
+        result: &mut Vec<(i32, String)>,
+    ) {
+        match pattern.next() {
+            Some(c) => {
+                if c == ' ' {
+                    for child in self.children(node) {
+                        partial.push(child.letter as char);
+                        self.fill_words(child, pattern.clone(), partial, result);
+                        partial.pop();
+                    }
+                } else if let Some(child) = self.child(node, c) {
+                    partial.push(child.letter as char);
+                    self.fill_words(child, pattern, partial, result);
+                    partial.pop();
+                }
+            }
+            None => {
+                if node.terminal {
+                    result.push((node.weight, partial.clone()));
+                }
+            }
+        }
+    }
+
+    fn is_valid<T: Iterator<Item = char> + Clone>(
+        &self,
+        node: &TrieNode,
+        mut chars: T,
+        min_weight: i32,
+    ) -> bool {
+        match chars.next() {
+            None => node.terminal && node.weight >= min_weight,
+            Some(' ') => self
+                .children(node)
+                .iter()
+                .any(|child| self.is_valid(child, chars.clone(), min_weight)),
+            Some(c) => match self.child(node, c) {
+                None => false,
+                Some(child) => self.is_valid(child, chars, min_weight),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Index {
+    trie: Trie,
+    /// Original spellings of the words that have one, e.g. "AT EASE" for ATEASE.
+    #[serde(default)]
+    display: FxHashMap<String, String>,
+}
+
+impl Index {
+    pub fn build(words: Vec<(String, i32)>) -> Index {
+        Index {
+            trie: Trie::build(words),
+            display: FxHashMap::default(),
+        }
+    }
+
+    /// Builds an index from entries that may contain spaces and punctuation, such as
+    /// "AT EASE". Entries are indexed by their letters alone and keep their original spelling
+    /// as display text.
+    pub fn build_with_display(entries: Vec<(String, i32)>) -> Index {
+        let words: Vec<(String, i32)> = entries
+            .iter()
+            .map(|(entry, weight)| (normalize_entry(entry), *weight))
+            .collect();
+        let mut index = Index::build(words);
+
+        for (entry, _) in entries.iter() {
+            index.set_display(&normalize_entry(entry), entry.trim());
+        }
+
+        index
+    }
+
+    /// Builds an index from the bundled `./WL-SP.txt`. Panics if the file can't be read.
+    pub fn build_default() -> Index {
+        Index::build_from_file("./WL-SP.txt").expect("could not read ./WL-SP.txt")
+    }
+
+    /// Builds an index from a word list with one word per line.
+    pub fn build_from_file(filename: impl AsRef<Path>) -> Result<Index, String> {
+        let lines = lines_from_file(filename)?
+            .into_iter()
+            .filter(|s| s.len() > 2)
+            .map(|w| (w, 0))
+            .collect();
+
+        Ok(Index::build(lines))
+    }
+
+    /// Like `build_from_file`, but lines may be phrases whose spacing and punctuation are kept
+    /// as display text. See `build_with_display`.
+    pub fn build_from_file_with_display(filename: impl AsRef<Path>) -> Result<Index, String> {
+        let lines = lines_from_file(filename)?
+            .into_iter()
+            .filter(|s| normalize_entry(s).len() > 2)
+            .map(|w| (w, 0))
+            .collect();
+
+        Ok(Index::build_with_display(lines))
+    }
+
+    /// Encodes the index in the compact binary format described in `binary`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        binary::encode(&self.trie, &self.display)
+    }
+
+    /// Decodes an index previously encoded with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Index, String> {
+        let (trie, display) = binary::decode(bytes)?;
+        Ok(Index { trie, display })
+    }
+
+    pub fn save(&self, filename: impl AsRef<Path>) -> Result<(), String> {
+        fs::write(filename, self.to_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(filename: impl AsRef<Path>) -> Result<Index, String> {
+        let bytes = fs::read(filename).map_err(|e| e.to_string())?;
+        Index::from_bytes(&bytes)
+    }
+
+    /// Loads either a binary index written by `save` or a plain word list.
+    pub fn open(filename: impl AsRef<Path>) -> Result<Index, String> {
+        let bytes = fs::read(&filename).map_err(|e| e.to_string())?;
+        if binary::is_index(&bytes) {
+            Index::from_bytes(&bytes)
+        } else {
+            Index::build_from_file(filename)
+        }
+    }
+
+    /// Returns the words matching `pattern`, highest weight first. Words of equal weight are
+    /// returned in alphabetical order.
+    pub fn words<T: Iterator<Item = char> + Clone>(&self, pattern: T) -> Vec<String> {
+        self.weighted_words(pattern)
+            .into_iter()
+            .map(|t| t.0)
+            .collect()
+    }
+
+    /// Returns the words matching `pattern` with their weights, in the same order as `words`.
+    pub fn weighted_words<T: Iterator<Item = char> + Clone>(
+        &self,
+        pattern: T,
+    ) -> Vec<(String, i32)> {
+        let mut result = Vec::with_capacity(4);
+        let mut partial = String::with_capacity(4);
+        self.trie
+            .fill_words(self.trie.root(), pattern, &mut partial, &mut result);
+
+        result.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        result.into_iter().map(|t| (t.1, t.0)).collect()
+    }
+
+    pub fn is_valid<T: Iterator<Item = char> + Clone>(&self, chars: T) -> bool {
+        self.trie.is_valid(self.trie.root(), chars, i32::MIN)
+    }
+
+    /// Like `is_valid`, but only counts words weighted at least `min_weight`.
+    pub fn is_valid_with_min_weight<T: Iterator<Item = char> + Clone>(
+        &self,
+        chars: T,
+        min_weight: i32,
+    ) -> bool {
+        self.trie.is_valid(self.trie.root(), chars, min_weight)
+    }
+
+    /// Looks up a single word, returning its weight and display text.
+    pub fn get(&self, word: &str) -> Option<WeightedWord> {
+        self.trie
+            .find(word)
+            .filter(|node| node.terminal)
+            .map(|node| self.with_display(node.weighted_word(word)))
+    }
+
+    /// The lowest weight of any word, or `None` if the index is empty.
+    pub fn min_weight(&self) -> Option<i32> {
+        self.trie.min_weight()
+    }
+
+    /// The original spelling of `word`, if it was indexed with one.
+    pub fn display(&self, word: &str) -> Option<&str> {
+        self.display.get(word).map(String::as_str)
+    }
+
+    /// Sets the original spelling of an indexed `word`. Spellings equal to the word itself
+    /// are not kept.
+    pub fn set_display(&mut self, word: &str, display: &str) {
+        if display != word && self.trie.find(word).is_some_and(|node| node.terminal) {
+            self.display
+                .insert(String::from(word), String::from(display));
+        }
+    }
+
+    fn with_display(&self, mut word: WeightedWord) -> WeightedWord {
+        word.display = self.display.get(&word.word).cloned();
+        word
+    }
+}
+
+/// The letters an entry is indexed by: its letters and digits, uppercased.
+pub fn normalize_entry(entry: &str) -> String {
+    entry
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Shuffles runs of equally weighted words in a list sorted by descending weight, where
+/// `weight` gives the weight of an item.
+pub fn shuffle_ties<T, R: Rng>(words: &mut [T], weight: impl Fn(&T) -> i32, rng: &mut R) {
+    let mut start = 0;
+    while start < words.len() {
+        let run = weight(&words[start]);
+        let end = start
+            + words[start..]
+                .iter()
+                .take_while(|w| weight(w) == run)
+                .count();
+        words[start..end].shuffle(rng);
+        start = end;
+    }
+}
+
+/// Reorders `words` at random, each next word being drawn from the rest with probability
+/// proportional to `exp(weight / temperature)`. High temperatures approach a uniform shuffle
+/// and low ones the weight order.
+pub fn sample_by_weight<T, R: Rng>(
+    words: &mut Vec<T>,
+    weight: impl Fn(&T) -> i32,
+    temperature: f64,
+    rng: &mut R,
+) {
+    // Adding Gumbel noise to the scaled weights and sorting samples without replacement.
+    let mut keyed: Vec<(f64, T)> = words
+        .drain(..)
+        .map(|word| {
+            let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+            (weight(&word) as f64 / temperature - (-u.ln()).ln(), word)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    words.extend(keyed.into_iter().map(|(_, word)| word));
+}
+
+fn lines_from_file(filename: impl AsRef<Path>) -> Result<Vec<String>, String> {
+    let file = File::open(&filename)
+        .map_err(|e| format!("Could not open {}: {}", filename.as_ref().display(), e))?;
+    let buf = BufReader::new(file);
+    buf.lines()
+        .map(|l| l.map_err(|e| format!("Could not read line: {}", e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Index;
+
+    #[test]
+    fn sampling_follows_temperature() {
+        use super::sample_by_weight;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let words: Vec<(String, i32)> = (0..5).map(|i| (format!("W{}", i), i * 10)).collect();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut cold = words.clone();
+        sample_by_weight(&mut cold, |w| w.1, 0.01, &mut rng);
+        let weights: Vec<i32> = cold.iter().map(|w| w.1).collect();
+        assert_eq!(weights, vec![40, 30, 20, 10, 0]);
+
+        // At a temperature of 10 the top word leads with probability e^4 / (1 + e + ... + e^4).
+        let firsts = (0..1000)
+            .filter(|_| {
+                let mut warm = words.clone();
+                sample_by_weight(&mut warm, |w| w.1, 10.0, &mut rng);
+                warm[0].1 == 40
+            })
+            .count();
+        assert!((560..700).contains(&firsts), "{}", firsts);
+    }
+
+    #[test]
+    fn build_real_index() {
+        let _index = Index::build_default();
+
+        assert!(Index::build_from_file("./missing.txt").is_err());
+        assert!(Index::open("./missing.txt").is_err());
+    }
+
+    #[test]
+    fn words_sorted_by_weight_then_alphabetically() {
+        let index = Index::build(vec![
+            (String::from("CAT"), 1),
+            (String::from("BAT"), 1),
+            (String::from("RAT"), 5),
+            (String::from("EAT"), 1),
+        ]);
+
+        assert_eq!(index.words(" AT".chars()), vec!["RAT", "BAT", "CAT", "EAT"]);
+        assert_eq!(index.min_weight(), Some(1));
+        assert!(index.is_valid_with_min_weight(" AT".chars(), 5));
+        assert!(!index.is_valid_with_min_weight(" AT".chars(), 6));
+        assert!(!index.is_valid_with_min_weight("CAT".chars(), 2));
+    }
+
+    #[test]
+    fn display_text() {
+        let index = Index::build_with_display(vec![
+            (String::from("at ease"), 3),
+            (String::from("ROCK 'N' ROLL"), 1),
+            (String::from("OREO"), 2),
+        ]);
+
+        assert_eq!(index.words("      ".chars()), vec!["ATEASE"]);
+        assert_eq!(index.display("ATEASE"), Some("at ease"));
+        assert_eq!(index.display("ROCKNROLL"), Some("ROCK 'N' ROLL"));
+        assert_eq!(index.display("OREO"), None);
+        assert_eq!(index.display("ATE"), None);
+
+        let loaded = Index::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(loaded.display("ATEASE"), Some("at ease"));
+        assert_eq!(
+            loaded.get("ATEASE").unwrap().display.as_deref(),
+            Some("at ease")
+        );
+
+        // Display text is attached to words as given, without normalizing them.
+        let mut index = Index::build(vec![(String::from("o'neill"), 1)]);
+        index.set_display("o'neill", "O'Neill");
+        index.set_display("missing", "Missing");
+        assert_eq!(index.display("o'neill"), Some("O'Neill"));
+        assert_eq!(index.display("missing"), None);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let index = Index::build(vec![
+            (String::from("BBBBB"), 2),
+            (String::from("AAAAA"), 1),
+            (String::from("AAB"), -3),
+        ]);
+
+        let bytes = index.to_bytes();
+        let loaded = Index::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded.words("A  ".chars()), vec!["AAB"]);
+        assert_eq!(loaded.words("     ".chars()), vec!["BBBBB", "AAAAA"]);
+        assert_eq!(loaded.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn binary_rejects_corruption() {
+        let index = Index::build(vec![(String::from("ABC"), 0)]);
+        let mut bytes = index.to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        assert!(Index::from_bytes(&bytes).is_err());
+        assert!(Index::from_bytes(b"XWIX").is_err());
+    }
+
+    #[test]
+    fn loaded_tries_are_checked() {
+        use super::{Trie, TrieNode};
+        use std::convert::TryFrom;
+
+        let node = |letter, child_count| TrieNode {
+            letter,
+            terminal: child_count == 0,
+            weight: 0,
+            first_child: 0,
+            child_count,
+        };
+        let trie = Trie::try_from(vec![node(0, 2), node(b'A', 0), node(b'B', 0)]).unwrap();
+        assert_eq!(trie.find("B").map(|node| node.terminal), Some(true));
+
+        // Children past the end, unaccounted for, or out of order.
+        assert!(Trie::try_from(vec![node(0, 99), node(b'A', 0)]).is_err());
+        assert!(Trie::try_from(vec![node(0, 1), node(b'A', 0), node(b'B', 0)]).is_err());
+        assert!(Trie::try_from(vec![node(0, 2), node(b'B', 0), node(b'A', 0)]).is_err());
+        assert!(Trie::try_from(vec![]).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Word list lookups beyond the fixed-length patterns used by the filler.
+//!
+//! Pattern syntax:
+//!
+//! * `A`-`Z`: that letter
+//! * `?`, `.` or `' '`: any single letter
+//! * `[AEIOU]`: one of the listed letters; `[^AEIOU]` or `[!AEIOU]`: none of them
+//! * `*`: any run of letters, including none
+//!
+//! Phrase constraints look at an entry's display text (see `Index::build_with_display`),
+//! split into words at spaces, hyphens and slashes. Entries without display text are a
+//! single word.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{normalize_entry, Index, Trie, TrieNode};
+use crate::WeightedWord;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Query {
+    pub pattern: Option<String>,
+    /// Regular expression the whole word must match, case-insensitively.
+    pub regex: Option<String>,
+    pub min_weight: Option<i32>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// One pattern per word of the entry, e.g. `["*", "X*"]` for two-word phrases whose
+    /// second word starts with X.
+    pub phrase: Option<Vec<String>>,
+    /// A whole word the entry must contain, e.g. CAT matches "CAT NAP" but not "CATNAP".
+    pub contains_word: Option<String>,
+    /// Number of matches to skip, for paging.
+    pub offset: usize,
+    /// Maximum number of matches to return. All are returned when unset.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    /// Number of matching words before paging.
+    pub total: usize,
+    /// The requested page of matches, highest weight first.
+    pub words: Vec<WeightedWord>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Element {
+    Letter(char),
+    Any,
+    Set { letters: Vec<char>, negated: bool },
+    Star,
+}
+
+impl Element {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Element::Letter(letter) => *letter == c,
+            Element::Any | Element::Star => true,
+            Element::Set { letters, negated } => letters.contains(&c) != *negated,
+        }
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Element>, String> {
+    let mut result = vec![];
+    let mut chars = pattern.chars().map(|c| c.to_ascii_uppercase());
+
+    while let Some(c) = chars.next() {
+        let element = match c {
+            '?' | '.' | ' ' => Element::Any,
+            '*' => Element::Star,
+            '[' => {
+                let mut letters = vec![];
+                let mut negated = false;
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => {
+                            closed = true;
+                            break;
+                        }
+                        '^' | '!' if letters.is_empty() && !negated => negated = true,
+                        c if c.is_ascii_alphabetic() => letters.push(c),
+                        c => return Err(format!("Unexpected '{}' in letter set.", c)),
+                    }
+                }
+                if !closed {
+                    return Err(String::from("Unclosed letter set."));
+                }
+                Element::Set { letters, negated }
+            }
+            c if c.is_ascii_alphabetic() => Element::Letter(c),
+            c => return Err(format!("Unexpected '{}' in pattern.", c)),
+        };
+
+        // Consecutive stars match the same words as a single one.
+        if element == Element::Star && result.last() == Some(&Element::Star) {
+            continue;
+        }
+        result.push(element);
+    }
+
+    Ok(result)
+}
+
+/// Whether `word` matches `pattern` in full.
+fn matches_word(pattern: &[Element], word: &[char]) -> bool {
+    match pattern.first() {
+        None => word.is_empty(),
+        Some(Element::Star) => {
+            (0..=word.len()).any(|skip| matches_word(&pattern[1..], &word[skip..]))
+        }
+        Some(element) => match word.first() {
+            Some(c) if element.matches(*c) => matches_word(&pattern[1..], &word[1..]),
+            _ => false,
+        },
+    }
+}
+
+/// The words of an entry's display text.
+fn phrase_words(word: &WeightedWord) -> Vec<Vec<char>> {
+    match &word.display {
+        None => vec![word.word.chars().collect()],
+        Some(display) => display
+            .split(|c: char| c.is_whitespace() || c == '-' || c == '/')
+            .map(normalize_entry)
+            .filter(|w| !w.is_empty())
+            .map(|w| w.chars().collect())
+            .collect(),
+    }
+}
+
+impl Trie {
+    fn query_words(
+        &self,
+        node: &TrieNode,
+        pattern: &[Element],
+        partial: &mut String,
+        result: &mut Vec<WeightedWord>,
+    ) {
+        match pattern.first() {
+            None => {
+                if node.terminal {
+                    result.push(node.weighted_word(partial));
+                }
+            }
+            Some(Element::Star) => {
+                // Either the star matches nothing more here, or it swallows one more letter.
+                self.query_words(node, &pattern[1..], partial, result);
+                for child in self.children(node) {
+                    partial.push(child.letter as char);
+                    self.query_words(child, pattern, partial, result);
+                    partial.pop();
+                }
+            }
+            Some(element) => {
+                for child in self.children(node) {
+                    if element.matches(child.letter as char) {
+                        partial.push(child.letter as char);
+                        self.query_words(child, &pattern[1..], partial, result);
+                        partial.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Index {
+    /// Finds the words matching every constraint in `query`, highest weight first and then
+    /// alphabetically.
+    pub fn query(&self, query: &Query) -> Result<QueryResult, String> {
+        let pattern = match &query.pattern {
+            Some(pattern) => parse_pattern(pattern)?,
+            None => vec![Element::Star],
+        };
+        let regex = match &query.regex {
+            Some(regex) => Some(
+                RegexBuilder::new(&format!("^(?:{})$", regex))
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            ),
+            None => None,
+        };
+        let phrase = match &query.phrase {
+            Some(phrase) => Some(
+                phrase
+                    .iter()
+                    .map(|p| parse_pattern(p))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+        let contains_word: Option<Vec<char>> = query
+            .contains_word
+            .as_ref()
+            .map(|w| normalize_entry(w).chars().collect());
+
+        let mut words = vec![];
+        let mut partial = String::new();
+        self.trie
+            .query_words(self.trie.root(), &pattern, &mut partial, &mut words);
+        words = words.into_iter().map(|w| self.with_display(w)).collect();
+
+        words.retain(|w| {
+            query.min_weight.is_none_or(|min| w.weight >= min)
+                && query.min_length.is_none_or(|min| w.word.len() >= min)
+                && query.max_length.is_none_or(|max| w.word.len() <= max)
+                && regex.as_ref().is_none_or(|r: &Regex| r.is_match(&w.word))
+        });
+        if phrase.is_some() || contains_word.is_some() {
+            words.retain(|w| {
+                let parts = phrase_words(w);
+                phrase.as_ref().is_none_or(|phrase| {
+                    phrase.len() == parts.len()
+                        && phrase
+                            .iter()
+                            .zip(parts.iter())
+                            .all(|(p, w)| matches_word(p, w))
+                }) && contains_word.as_ref().is_none_or(|c| parts.contains(c))
+            });
+        }
+        words.sort_unstable_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        // A word can be reached more than once when the pattern has several stars.
+        words.dedup();
+
+        let total = words.len();
+        let words = words
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(QueryResult { total, words })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Query;
+    use crate::index::Index;
+
+    fn index() -> Index {
+        Index::build(vec![
+            (String::from("ARENA"), 10),
+            (String::from("AREA"), 5),
+            (String::from("ORE"), 5),
+            (String::from("OREO"), 20),
+            (String::from("ERA"), 1),
+        ])
+    }
+
+    fn words(index: &Index, query: &Query) -> Vec<String> {
+        let result = index.query(query).unwrap();
+        result.words.into_iter().map(|w| w.word).collect()
+    }
+
+    #[test]
+    fn wildcards_and_sets() {
+        let index = index();
+        let query = |pattern: &str| Query {
+            pattern: Some(String::from(pattern)),
+            ..Query::default()
+        };
+
+        assert_eq!(words(&index, &query("AR?NA")), vec!["ARENA"]);
+        assert_eq!(
+            words(&index, &query("[AO]RE*")),
+            vec!["OREO", "ARENA", "AREA", "ORE"]
+        );
+        assert_eq!(words(&index, &query("[^A]R*")), vec!["OREO", "ORE", "ERA"]);
+        assert_eq!(words(&index, &query("*A")), vec!["ARENA", "AREA", "ERA"]);
+        assert_eq!(words(&index, &query("*E*")).len(), 5);
+        assert!(index.query(&query("[AB")).is_err());
+    }
+
+    #[test]
+    fn regex_weight_and_paging() {
+        let index = index();
+
+        let query = Query {
+            regex: Some(String::from("(ore|era).*")),
+            min_weight: Some(2),
+            ..Query::default()
+        };
+        assert_eq!(words(&index, &query), vec!["OREO", "ORE"]);
+
+        let query = Query {
+            offset: 1,
+            limit: Some(2),
+            ..Query::default()
+        };
+        let result = index.query(&query).unwrap();
+        assert_eq!(result.total, 5);
+        assert_eq!(
+            result.words.into_iter().map(|w| w.word).collect::<Vec<_>>(),
+            vec!["ARENA", "AREA"]
+        );
+    }
+
+    #[test]
+    fn phrases() {
+        let index = Index::build_with_display(vec![
+            (String::from("AT EASE"), 0),
+            (String::from("CAT NAP"), 0),
+            (String::from("CATBIRD"), 0),
+            (String::from("TOP CAT"), 0),
+            (String::from("TOP-DOWN"), 0),
+        ]);
+
+        let query = Query {
+            phrase: Some(vec![String::from("*"), String::from("C*")]),
+            ..Query::default()
+        };
+        assert_eq!(words(&index, &query), vec!["TOPCAT"]);
+
+        let query = Query {
+            contains_word: Some(String::from("cat")),
+            ..Query::default()
+        };
+        assert_eq!(words(&index, &query), vec!["CATNAP", "TOPCAT"]);
+
+        let query = Query {
+            phrase: Some(vec![String::from("TOP"), String::from("*")]),
+            ..Query::default()
+        };
+        let result = index.query(&query).unwrap();
+        assert_eq!(result.total, 2);
+        assert_eq!(result.words[1].display.as_deref(), Some("TOP-DOWN"));
+    }
+}
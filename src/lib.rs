@@ -1,66 +1,364 @@
 extern crate js_sys;
 extern crate wasm_bindgen;
 
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+compile_error!("the `parallel` feature spawns threads and is not supported on wasm32");
+
+pub mod analysis;
 pub mod crossword;
 pub mod fill;
 pub mod index;
+pub mod theme;
 
-use crate::crossword::Crossword;
+use crate::crossword::{Crossword, EntryLocation};
 
-use self::js_sys::Array;
-use fill::{EntryLocationToFill, Fill, Filler};
-use index::Index;
+use analysis::{
+    heat::heat_map,
+    report::{fill_report, ReportEntry},
+    suggest::suggest,
+};
+use fill::{
+    cache::FillCache,
+    search::{FillSearch, FillStatus},
+    EntryLocationToFill, Fill, FillOptions, Filler,
+};
+use index::{query::Query, Index};
 use js_sys::{JsString, Number};
 use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, rc::Rc};
+use theme::{find_theme_pairs, Transformation};
 use wasm_bindgen::prelude::*;
-use web_sys::console;
 
 #[wasm_bindgen]
 pub struct Solver {
-    index: Index,
+    index: Rc<Index>,
+    cache: Rc<RefCell<FillCache>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WeightedWord {
-    word: String,
-    weight: i32,
+    pub word: String,
+    pub weight: i32,
+    /// Original spelling, e.g. "AT EASE", when it differs from `word`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
 }
 
 #[wasm_bindgen]
 impl Solver {
     pub fn new(words_arr: &JsValue) -> Solver {
-        let words: Vec<WeightedWord> = words_arr.into_serde().unwrap();
+        let words: Vec<WeightedWord> = serde_wasm_bindgen::from_value(words_arr.clone()).unwrap();
+
+        let mut index = Index::build(words.iter().map(|w| (w.word.clone(), w.weight)).collect());
+        for w in words.iter() {
+            if let Some(display) = &w.display {
+                index.set_display(&w.word, display);
+            }
+        }
+        Solver::with_index(index)
+    }
+
+    /// Creates a solver from an index encoded with `Index::to_bytes`.
+    pub fn from_index_bytes(bytes: &[u8]) -> Result<Solver, JsValue> {
+        let index = Index::from_bytes(bytes).map_err(|e| JsValue::from_str(&e))?;
+        Ok(Solver::with_index(index))
+    }
+
+    /// Encodes the loaded index so it can be cached and passed to `from_index_bytes`.
+    pub fn index_bytes(&self) -> Vec<u8> {
+        self.index.to_bytes()
+    }
 
-        let index = Index::build(words.into_iter().map(|w| (w.word, w.weight)).collect());
-        Solver { index }
+    /// Sets the memory budget, in bytes, of the word caches kept warm between fills.
+    pub fn set_cache_limit(&mut self, bytes: usize) {
+        self.cache.borrow_mut().set_capacity_bytes(bytes);
     }
 
+    pub fn clear_cache(&mut self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    /// Fills the grid, or only the entries in `clues_to_fill`. Blanks left by a partial fill
+    /// keep their letter sets in the returned grid, written as in the input. Throws the
+    /// reason when the options or entries are invalid or no fill exists.
     pub fn solve(
+        &mut self,
+        grid: JsString,
+        rows: Number,
+        cols: Number,
+        clues_to_fill: JsValue,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options = parse_options(options)?;
+        let crossword = parse_crossword(grid, rows, cols);
+        let entry_locations_to_fill = parse_entry_locations_to_fill(clues_to_fill)?;
+
+        let mut filler = Filler::with_cache(&self.index, &options, self.cache.take());
+        let result = filler.fill(&crossword, entry_locations_to_fill.as_ref());
+        self.cache.replace(filler.into_cache());
+
+        let result = result.map_err(|e| JsValue::from_str(&e))?;
+        Ok(result.to_grid_string().into())
+    }
+
+    /// Like `solve`, but lowers `options.min_weight` until a fill exists and reports how far.
+    /// Returns
+    /// `{grid, min_weight, below_min_weight: [{start_row, start_col, direction, word,
+    /// weight}]}`.
+    pub fn solve_relaxed(
+        &mut self,
+        grid: JsString,
+        rows: Number,
+        cols: Number,
+        clues_to_fill: JsValue,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options = parse_options(options)?;
+        let crossword = parse_crossword(grid, rows, cols);
+        let entry_locations_to_fill = parse_entry_locations_to_fill(clues_to_fill)?;
+
+        let mut filler = Filler::with_cache(&self.index, &options, self.cache.take());
+        let result = filler.fill_relaxed(&crossword, entry_locations_to_fill.as_ref());
+        self.cache.replace(filler.into_cache());
+
+        let result = result.map_err(|e| JsValue::from_str(&e))?;
+        Ok(to_js(&RelaxedReport {
+            grid: result.crossword.to_grid_string(),
+            min_weight: result.min_weight,
+            below_min_weight: result.below_min_weight,
+        })?)
+    }
+
+    /// Fills only the blank cells in `cells`, given as `[[row, col], ...]`, leaving the rest of
+    /// the grid as it is. Entries partly outside the region must still admit a word through
+    /// the letters placed. Fails if the region has no blank cells. Blank cells left outside
+    /// the region keep their letter sets, e.g. `[AEIOU]`, in the returned grid.
+    pub fn solve_region(
+        &mut self,
+        grid: JsString,
+        rows: Number,
+        cols: Number,
+        cells: JsValue,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options = parse_options(options)?;
+        let crossword = parse_crossword(grid, rows, cols);
+        let cells: Vec<(usize, usize)> = serde_wasm_bindgen::from_value(cells)?;
+
+        let mut filler = Filler::with_cache(&self.index, &options, self.cache.take());
+        let result = filler.fill_region(&crossword, &cells);
+        self.cache.replace(filler.into_cache());
+
+        result
+            .map(|crossword| crossword.to_grid_string().into())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Every word fitting the entry `{start_row, start_col, direction}`, highest weight first.
+    /// Returns `[{word, weight, viable, crossing_counts}]`, where `viable` tells whether all
+    /// crossings can still be completed with the word in place.
+    pub fn suggestions(
+        &mut self,
+        grid: JsString,
+        rows: Number,
+        cols: Number,
+        entry: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let crossword = parse_crossword(grid, rows, cols);
+        let entry: EntryLocationToFill = serde_wasm_bindgen::from_value(entry)?;
+        let entry_location =
+            EntryLocation::new(entry.start_row, entry.start_col, 0, entry.direction, false);
+
+        let suggestions = suggest(
+            &self.index,
+            &mut self.cache.borrow_mut(),
+            &crossword,
+            &entry_location,
+        );
+        Ok(to_js(&suggestions)?)
+    }
+
+    /// Feasible letter counts per cell and candidate counts per entry. Returns
+    /// `{cells: [[count or null]], entries: [{start_row, start_col, direction, length,
+    /// candidates}]}`.
+    pub fn heat_map(&mut self, grid: JsString, rows: Number, cols: Number) -> JsValue {
+        let crossword = parse_crossword(grid, rows, cols);
+        let heat = heat_map(&self.index, &mut self.cache.borrow_mut(), &crossword);
+        to_js(&heat).unwrap()
+    }
+
+    /// Statistics about a filled grid: word count, lengths, letters, weights and entries
+    /// missing from the index. See `FillReport` for the fields.
+    pub fn report(&self, grid: JsString, rows: Number, cols: Number) -> JsValue {
+        let crossword = parse_crossword(grid, rows, cols);
+        to_js(&fill_report(&self.index, &crossword)).unwrap()
+    }
+
+    /// Looks up words matching a `Query`, e.g. `{pattern: "A?E??N?", min_weight: 50, limit: 20}`.
+    /// Returns `{total, words: [{word, weight}]}`.
+    pub fn query(&self, query: JsValue) -> Result<JsValue, JsValue> {
+        let query: Query = serde_wasm_bindgen::from_value(query)?;
+        let result = self
+            .index
+            .query(&query)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(to_js(&result)?)
+    }
+
+    /// Words using exactly `letters`, where `?` is a blank. Returns `[{word, weight}]`.
+    pub fn anagrams(&self, letters: &str) -> Result<JsValue, JsValue> {
+        Ok(to_js(&self.index.anagrams(letters))?)
+    }
+
+    /// Words that can be spelled from some of `letters`, longest first.
+    pub fn sub_anagrams(&self, letters: &str) -> Result<JsValue, JsValue> {
+        Ok(to_js(&self.index.sub_anagrams(letters))?)
+    }
+
+    /// Words containing all of `letters` in any order.
+    pub fn words_containing_letters(&self, letters: &str) -> Result<JsValue, JsValue> {
+        Ok(to_js(&self.index.words_containing_letters(letters))?)
+    }
+
+    /// Finds theme pairs for a `Transformation`, e.g. `{InsertLetter: "S"}` or `"Reversal"`.
+    /// Returns `[{length, pairs: [{base, transformed}]}]`.
+    pub fn theme_pairs(&self, transformation: JsValue) -> Result<JsValue, JsValue> {
+        let transformation: Transformation = serde_wasm_bindgen::from_value(transformation)?;
+        Ok(to_js(&find_theme_pairs(&self.index, &transformation))?)
+    }
+
+    /// Starts a fill that is advanced with `FillHandle::step` instead of running to
+    /// completion, so it can be pumped from a worker or animation frame loop. Throws if the
+    /// options or entries cannot be read; other invalid options are reported by `step`.
+    pub fn start_fill(
         &self,
         grid: JsString,
         rows: Number,
         cols: Number,
         clues_to_fill: JsValue,
-    ) -> JsValue {
-        let mut filler = Filler::new(&self.index);
-        let crossword = Crossword::from_string(
-            grid.as_string().unwrap(),
-            cols.as_f64().unwrap() as usize,
-            rows.as_f64().unwrap() as usize,
-        )
-        .unwrap();
-
-        match clues_to_fill.is_undefined() {
-            true => {
-                let candidate = filler.fill(&crossword, None).unwrap();
-                candidate.contents.into()
-            }
-            false => {
-                let parsed: Vec<EntryLocationToFill> = clues_to_fill.into_serde().unwrap();
-                // return Some(&parsed);
-                let candidate = filler.fill(&crossword, Some(&parsed)).unwrap();
-                candidate.contents.into()
-            }
+        options: JsValue,
+    ) -> Result<FillHandle, JsValue> {
+        let options = parse_options(options)?;
+        let crossword = parse_crossword(grid, rows, cols);
+        let entry_locations_to_fill = parse_entry_locations_to_fill(clues_to_fill)?;
+
+        let search = Filler::with_options(&self.index, &options)
+            .start(&crossword, entry_locations_to_fill.as_ref());
+
+        Ok(FillHandle {
+            index: self.index.clone(),
+            cache: self.cache.clone(),
+            options,
+            search,
+        })
+    }
+}
+
+impl Solver {
+    fn with_index(index: Index) -> Solver {
+        Solver {
+            index: Rc::new(index),
+            cache: Rc::new(RefCell::new(FillCache::default())),
         }
     }
 }
+
+/// An in-progress fill started with `Solver::start_fill`.
+#[wasm_bindgen]
+pub struct FillHandle {
+    index: Rc<Index>,
+    cache: Rc<RefCell<FillCache>>,
+    options: FillOptions,
+    search: FillSearch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct RelaxedReport {
+    grid: String,
+    min_weight: Option<i32>,
+    below_min_weight: Vec<ReportEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct StepReport {
+    status: &'static str,
+    nodes: usize,
+    restarts: usize,
+    grid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[wasm_bindgen]
+impl FillHandle {
+    /// Expands at most `budget` search nodes. Returns `{status, nodes, restarts, grid, error}`
+    /// where `status` is one of "running", "filled", "failed", "cancelled" or "invalid", `grid`
+    /// is set once filled and `error` explains invalid options.
+    pub fn step(&mut self, budget: u32) -> JsValue {
+        let mut filler = Filler::with_cache(&self.index, &self.options, self.cache.take());
+        filler.step(&mut self.search, budget as usize);
+        self.cache.replace(filler.into_cache());
+
+        self.report()
+    }
+
+    /// Stops the fill. Later calls to `step` report "cancelled".
+    pub fn cancel(&mut self) {
+        self.search.cancel();
+    }
+
+    pub fn status(&self) -> JsValue {
+        self.report()
+    }
+}
+
+impl FillHandle {
+    fn report(&self) -> JsValue {
+        let (status, grid, error) = match self.search.status() {
+            FillStatus::Running => ("running", None, None),
+            FillStatus::Filled(result) => ("filled", Some(result.to_grid_string()), None),
+            FillStatus::Failed => ("failed", None, None),
+            FillStatus::Cancelled => ("cancelled", None, None),
+            FillStatus::Invalid(e) => ("invalid", None, Some(e.clone())),
+        };
+        let report = StepReport {
+            status,
+            nodes: self.search.nodes(),
+            restarts: self.search.restarts(),
+            grid,
+            error,
+        };
+        to_js(&report).unwrap()
+    }
+}
+
+/// Converts `value` for JS the way the docs describe it: `None` as `null` and maps as plain
+/// objects.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+}
+
+fn parse_options(options: JsValue) -> Result<FillOptions, JsValue> {
+    match options.is_undefined() {
+        true => Ok(FillOptions::default()),
+        false => Ok(serde_wasm_bindgen::from_value(options)?),
+    }
+}
+
+fn parse_crossword(grid: JsString, rows: Number, cols: Number) -> Crossword {
+    Crossword::from_string(
+        grid.as_string().unwrap(),
+        cols.as_f64().unwrap() as usize,
+        rows.as_f64().unwrap() as usize,
+    )
+    .unwrap()
+}
+
+fn parse_entry_locations_to_fill(
+    clues_to_fill: JsValue,
+) -> Result<Option<Vec<EntryLocationToFill>>, JsValue> {
+    match clues_to_fill.is_undefined() {
+        true => Ok(None),
+        false => Ok(Some(serde_wasm_bindgen::from_value(clues_to_fill)?)),
+    }
+}
@@ -1,5 +1,235 @@
-mod crossword;
+use std::{env, fs, process, time::Instant};
+
+use fillers::{
+    analysis::{heat::heat_map, report::fill_report},
+    crossword::Crossword,
+    fill::cache::FillCache,
+    index::{query::Query, Index},
+    theme::{find_theme_pairs, Transformation},
+    WeightedWord,
+};
+
+const USAGE: &str = "Usage:
+  fillers build-index <word-list> <output> [--display]
+  fillers query <index-or-word-list> [pattern] [--regex <regex>] [--min-weight <n>]
+                [--min-length <n>] [--max-length <n>] [--offset <n>] [--limit <n>]
+                [--phrase <word patterns>] [--contains-word <word>]
+  fillers anagram <index-or-word-list> <letters> [--mode exact|sub|contains]
+  fillers theme <index-or-word-list> insert [letter] | delete [letter] | substitute <from> <to>
+                | hidden <word> | reverse
+  fillers heat-map <index-or-word-list> <grid-file>
+  fillers report <index-or-word-list> <grid-file>
+
+Grid files have one row per line: letters, ' ' for blanks, '*' for blocks and letter sets
+such as [AEIOU] for constrained blanks.";
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("build-index") => build_index(&args[1..]),
+        Some("query") => query(&args[1..]),
+        Some("anagram") => anagram(&args[1..]),
+        Some("theme") => theme(&args[1..]),
+        Some("heat-map") => heat(&args[1..]),
+        Some("report") => report(&args[1..]),
+        _ => Err(String::from(USAGE)),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn build_index(args: &[String]) -> Result<(), String> {
+    let (positional, flags) = parse_args(args, &["--display"])?;
+    if positional.len() != 2 {
+        return Err(String::from(USAGE));
+    }
+
+    let mut keep_display = false;
+    for (flag, _) in flags {
+        match flag.as_str() {
+            "--display" => keep_display = true,
+            _ => return Err(format!("Unknown option {}.\n{}", flag, USAGE)),
+        }
+    }
+
+    let now = Instant::now();
+    let index = match keep_display {
+        true => Index::build_from_file_with_display(&positional[0])?,
+        false => Index::build_from_file(&positional[0])?,
+    };
+    index.save(&positional[1])?;
+    println!(
+        "Wrote {} in {} ms.",
+        positional[1],
+        now.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+fn query(args: &[String]) -> Result<(), String> {
+    let (positional, flags) = parse_args(args, &[])?;
+    if positional.is_empty() || positional.len() > 2 {
+        return Err(String::from(USAGE));
+    }
+
+    let mut query = Query {
+        pattern: positional.get(1).cloned(),
+        ..Query::default()
+    };
+    for (flag, value) in flags {
+        match flag.as_str() {
+            "--regex" => query.regex = Some(value),
+            "--min-weight" => query.min_weight = Some(parse_number(&flag, &value)?),
+            "--min-length" => query.min_length = Some(parse_number(&flag, &value)?),
+            "--max-length" => query.max_length = Some(parse_number(&flag, &value)?),
+            "--offset" => query.offset = parse_number(&flag, &value)?,
+            "--limit" => query.limit = Some(parse_number(&flag, &value)?),
+            "--phrase" => query.phrase = Some(value.split_whitespace().map(String::from).collect()),
+            "--contains-word" => query.contains_word = Some(value),
+            _ => return Err(format!("Unknown option {}.\n{}", flag, USAGE)),
+        }
+    }
+
+    let index = Index::open(&positional[0])?;
+    let result = index.query(&query)?;
+    print_words(&result.words);
+    println!("{} of {} matches.", result.words.len(), result.total);
+    Ok(())
+}
+
+fn anagram(args: &[String]) -> Result<(), String> {
+    let (positional, flags) = parse_args(args, &[])?;
+    if positional.len() != 2 {
+        return Err(String::from(USAGE));
+    }
+
+    let mut mode = String::from("exact");
+    for (flag, value) in flags {
+        match flag.as_str() {
+            "--mode" => mode = value,
+            _ => return Err(format!("Unknown option {}.\n{}", flag, USAGE)),
+        }
+    }
+
+    let index = Index::open(&positional[0])?;
+    let letters = &positional[1];
+    let words = match mode.as_str() {
+        "exact" => index.anagrams(letters),
+        "sub" => index.sub_anagrams(letters),
+        "contains" => index.words_containing_letters(letters),
+        _ => return Err(format!("Unknown mode {}.\n{}", mode, USAGE)),
+    };
+    print_words(&words);
+    println!("{} matches.", words.len());
+    Ok(())
+}
+
+fn theme(args: &[String]) -> Result<(), String> {
+    let (positional, _) = parse_args(args, &[])?;
+    let letter = |i: usize| positional.get(i).and_then(|l| l.chars().next());
+
+    let transformation = match positional.get(1).map(String::as_str) {
+        Some("insert") => Transformation::InsertLetter(letter(2)),
+        Some("delete") => Transformation::DeleteLetter(letter(2)),
+        Some("substitute") if positional.len() == 4 => Transformation::Substitute {
+            from: positional[2].clone(),
+            to: positional[3].clone(),
+        },
+        Some("hidden") if positional.len() == 3 => {
+            Transformation::HiddenWord(positional[2].clone())
+        }
+        Some("reverse") => Transformation::Reversal,
+        _ => return Err(String::from(USAGE)),
+    };
+
+    let index = Index::open(&positional[0])?;
+    for group in find_theme_pairs(&index, &transformation) {
+        println!("{} letters:", group.length);
+        for pair in group.pairs {
+            println!(
+                "  {} -> {}\t{}",
+                pair.base.word,
+                pair.transformed.word,
+                pair.weight()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn heat(args: &[String]) -> Result<(), String> {
+    let (positional, _) = parse_args(args, &[])?;
+    if positional.len() != 2 {
+        return Err(String::from(USAGE));
+    }
+
+    let index = Index::open(&positional[0])?;
+    let crossword = read_grid(&positional[1])?;
+    print!(
+        "{}",
+        heat_map(&index, &mut FillCache::default(), &crossword)
+    );
+    Ok(())
+}
+
+fn report(args: &[String]) -> Result<(), String> {
+    let (positional, _) = parse_args(args, &[])?;
+    if positional.len() != 2 {
+        return Err(String::from(USAGE));
+    }
+
+    let index = Index::open(&positional[0])?;
+    let crossword = read_grid(&positional[1])?;
+    print!("{}", fill_report(&index, &crossword));
+    Ok(())
+}
+
+fn read_grid(path: &str) -> Result<Crossword, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    Crossword::from_rows(&contents)
+}
+
+fn print_words(words: &[WeightedWord]) {
+    for word in words.iter() {
+        match &word.display {
+            Some(display) => println!("{}\t{}\t{}", word.word, word.weight, display),
+            None => println!("{}\t{}", word.word, word.weight),
+        }
+    }
+}
+
+/// `--flag value` pairs, in the order given.
+type Flags = Vec<(String, String)>;
+
+/// Splits arguments into positional ones and flags. Flags listed in `switches` take no value.
+fn parse_args(args: &[String], switches: &[&str]) -> Result<(Vec<String>, Flags), String> {
+    let mut positional = vec![];
+    let mut flags = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if switches.contains(&arg.as_str()) {
+            flags.push((arg.clone(), String::new()));
+        } else if arg.starts_with("--") {
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("Missing value for {}.", arg))?;
+            flags.push((arg.clone(), value.clone()));
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+
+    Ok((positional, flags))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {} for {}.", value, flag))
 }
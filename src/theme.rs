@@ -0,0 +1,250 @@
+//! Theme exploration: pairs of entries related by a transformation, where both the base and
+//! the transformed entry are in the word list.
+
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{normalize_entry, query::Query, Index},
+    WeightedWord,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Transformation {
+    /// Insert a letter anywhere, e.g. OWL -> BOWL. `None` allows any letter.
+    InsertLetter(Option<char>),
+    /// Delete a letter, e.g. BOWL -> OWL. `None` allows any letter.
+    DeleteLetter(Option<char>),
+    /// Replace one occurrence of `from` with `to`, e.g. CAT -> COT.
+    Substitute { from: String, to: String },
+    /// Phrases hiding a word across one of their word boundaries, e.g. OWL in "NOW LEAVING".
+    /// Only entries indexed with display text have word boundaries.
+    HiddenWord(String),
+    /// The word reversed, e.g. STRESSED -> DESSERTS.
+    Reversal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemePair {
+    pub base: WeightedWord,
+    pub transformed: WeightedWord,
+}
+
+impl ThemePair {
+    /// A pair is only as good as its weaker entry.
+    pub fn weight(&self) -> i32 {
+        self.base.weight.min(self.transformed.weight)
+    }
+}
+
+/// Theme pairs whose transformed entries share a length, so they can be placed symmetrically.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeGroup {
+    pub length: usize,
+    pub pairs: Vec<ThemePair>,
+}
+
+/// Finds every pair related by `transformation`, grouped by the length of the transformed
+/// entry (longest first) and ranked by weight within each group.
+pub fn find_theme_pairs(index: &Index, transformation: &Transformation) -> Vec<ThemeGroup> {
+    let words = index.query(&Query::default()).unwrap().words;
+    let mut pairs = vec![];
+    let mut seen = FxHashSet::default();
+
+    for word in words.iter() {
+        for (base, transformed) in related(index, word, transformation) {
+            if seen.insert((base.word.clone(), transformed.word.clone())) {
+                pairs.push(ThemePair { base, transformed });
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| {
+        b.transformed
+            .word
+            .len()
+            .cmp(&a.transformed.word.len())
+            .then_with(|| b.weight().cmp(&a.weight()))
+            .then_with(|| a.transformed.word.cmp(&b.transformed.word))
+            .then_with(|| a.base.word.cmp(&b.base.word))
+    });
+
+    let mut groups: Vec<ThemeGroup> = vec![];
+    for pair in pairs {
+        let length = pair.transformed.word.len();
+        match groups.last_mut() {
+            Some(group) if group.length == length => group.pairs.push(pair),
+            _ => groups.push(ThemeGroup {
+                length,
+                pairs: vec![pair],
+            }),
+        }
+    }
+    groups
+}
+
+/// The (base, transformed) pairs in which `word` takes part. Insertions are found from the
+/// longer word so that each word only needs its deletions checked.
+fn related(
+    index: &Index,
+    word: &WeightedWord,
+    transformation: &Transformation,
+) -> Vec<(WeightedWord, WeightedWord)> {
+    match transformation {
+        Transformation::InsertLetter(letter) => deletions(index, word, *letter)
+            .into_iter()
+            .map(|shorter| (shorter, word.clone()))
+            .collect(),
+        Transformation::DeleteLetter(letter) => deletions(index, word, *letter)
+            .into_iter()
+            .map(|shorter| (word.clone(), shorter))
+            .collect(),
+        Transformation::Substitute { from, to } => {
+            let from = normalize_entry(from);
+            let to = normalize_entry(to);
+            if from.is_empty() {
+                return vec![];
+            }
+            word.word
+                .match_indices(from.as_str())
+                .filter_map(|(i, _)| {
+                    let candidate =
+                        format!("{}{}{}", &word.word[..i], to, &word.word[i + from.len()..]);
+                    index.get(&candidate)
+                })
+                .map(|transformed| (word.clone(), transformed))
+                .collect()
+        }
+        Transformation::HiddenWord(hidden) => {
+            let hidden = normalize_entry(hidden);
+            match (&word.display, index.get(&hidden)) {
+                (Some(display), Some(base)) if hides_across_boundary(display, &hidden) => {
+                    vec![(base, word.clone())]
+                }
+                _ => vec![],
+            }
+        }
+        Transformation::Reversal => {
+            let reversed: String = word.word.chars().rev().collect();
+            // Report each pair once, from its alphabetically first word.
+            if reversed <= word.word {
+                return vec![];
+            }
+            index
+                .get(&reversed)
+                .map(|transformed| vec![(word.clone(), transformed)])
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Words in the index obtained by deleting one `letter` (or any letter) from `word`.
+fn deletions(index: &Index, word: &WeightedWord, letter: Option<char>) -> Vec<WeightedWord> {
+    let mut result = vec![];
+    for (i, c) in word.word.char_indices() {
+        if letter.is_some_and(|l| l.to_ascii_uppercase() != c) {
+            continue;
+        }
+        // Deleting either letter of a double letter gives the same word.
+        if i > 0 && word.word[..i].ends_with(c) {
+            continue;
+        }
+        let candidate = format!("{}{}", &word.word[..i], &word.word[i + c.len_utf8()..]);
+        if let Some(shorter) = index.get(&candidate) {
+            result.push(shorter);
+        }
+    }
+    result
+}
+
+/// Whether `hidden` occurs in the letters of `display` starting in one word and ending in a
+/// later one.
+fn hides_across_boundary(display: &str, hidden: &str) -> bool {
+    let mut letters = String::new();
+    let mut word_starts = vec![];
+    for part in display.split(|c: char| c.is_whitespace() || c == '-' || c == '/') {
+        let part = normalize_entry(part);
+        if !part.is_empty() {
+            word_starts.push(letters.len());
+            letters.push_str(&part);
+        }
+    }
+
+    letters.match_indices(hidden).any(|(start, _)| {
+        let end = start + hidden.len();
+        word_starts.iter().any(|b| start < *b && *b < end)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_theme_pairs, Transformation};
+    use crate::index::Index;
+
+    fn pairs(index: &Index, transformation: Transformation) -> Vec<(usize, String, String)> {
+        find_theme_pairs(index, &transformation)
+            .into_iter()
+            .flat_map(|group| {
+                let length = group.length;
+                group
+                    .pairs
+                    .into_iter()
+                    .map(move |p| (length, p.base.word, p.transformed.word))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn letter_transformations() {
+        let index = Index::build(vec![
+            (String::from("OWL"), 5),
+            (String::from("BOWL"), 5),
+            (String::from("HOWL"), 1),
+            (String::from("BOWLS"), 2),
+            (String::from("CAT"), 0),
+            (String::from("COT"), 0),
+            (String::from("TAC"), 0),
+        ]);
+
+        assert_eq!(
+            pairs(&index, Transformation::InsertLetter(None)),
+            vec![
+                (5, String::from("BOWL"), String::from("BOWLS")),
+                (4, String::from("OWL"), String::from("BOWL")),
+                (4, String::from("OWL"), String::from("HOWL")),
+            ]
+        );
+        assert_eq!(
+            pairs(&index, Transformation::DeleteLetter(Some('s'))),
+            vec![(4, String::from("BOWLS"), String::from("BOWL"))]
+        );
+        assert_eq!(
+            pairs(
+                &index,
+                Transformation::Substitute {
+                    from: String::from("A"),
+                    to: String::from("O"),
+                }
+            ),
+            vec![(3, String::from("CAT"), String::from("COT"))]
+        );
+        assert_eq!(
+            pairs(&index, Transformation::Reversal),
+            vec![(3, String::from("CAT"), String::from("TAC"))]
+        );
+    }
+
+    #[test]
+    fn hidden_words() {
+        let index = Index::build_with_display(vec![
+            (String::from("OWL"), 0),
+            (String::from("NOW LEAVING"), 0),
+            (String::from("HOWLING"), 0),
+        ]);
+
+        assert_eq!(
+            pairs(&index, Transformation::HiddenWord(String::from("owl"))),
+            vec![(10, String::from("OWL"), String::from("NOWLEAVING"))]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Shapes of the values the wasm API hands to JS. Run with `wasm-pack test --node`.
+
+#![cfg(target_arch = "wasm32")]
+
+use fillers::Solver;
+use js_sys::{Array, JsString, Map, Number, Object, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn solver() -> Solver {
+    let words = Array::new();
+    for word in ["CAT", "COT", "AT", "TO"] {
+        let entry = Object::new();
+        Reflect::set(&entry, &"word".into(), &word.into()).unwrap();
+        Reflect::set(&entry, &"weight".into(), &JsValue::from(0)).unwrap();
+        words.push(&entry);
+    }
+    Solver::new(&words)
+}
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &key.into()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn missing_values_are_null() {
+    let mut solver = solver();
+    let heat = solver.heat_map(JsString::from("CA*"), Number::from(1.0), Number::from(3.0));
+    let row = Array::from(&Array::from(&get(&heat, "cells")).get(0));
+    assert!(row.get(0).as_f64().is_some());
+    assert!(row.get(2).is_null());
+
+    // XYZ is complete but not in the index, so no weight is known.
+    let report = solver.report(JsString::from("XYZ"), Number::from(1.0), Number::from(3.0));
+    assert!(get(&report, "min_weight").is_null());
+    assert!(get(&report, "average_weight").is_null());
+}
+
+#[wasm_bindgen_test]
+fn maps_are_plain_objects() {
+    let solver = solver();
+    let report = solver.report(JsString::from("CAT"), Number::from(1.0), Number::from(3.0));
+    let letter_counts = get(&report, "letter_counts");
+    assert!(letter_counts.is_object());
+    assert!(!letter_counts.is_instance_of::<Map>());
+    assert_eq!(get(&letter_counts, "C").as_f64(), Some(1.0));
+}